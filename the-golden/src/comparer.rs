@@ -0,0 +1,191 @@
+use std::io::{IsTerminal, Read};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::interpreter::{Interpreter, Snapshot};
+use crate::tester::{Tester, DEFAULT_TIMEOUT};
+use crate::{Diagnostic, Flags, Utils, INFO_PREFIX_LENGTH};
+
+// What one version printed and the state it ended in
+struct Execution {
+	version: String,
+	output: Vec<u8>,
+	snapshot: Option<Snapshot>,
+}
+
+pub struct Comparer {
+	flags: Flags,
+	ansi_enabled: bool,
+}
+
+impl Comparer {
+	pub fn new(flags: Flags, ansi_enabled: bool) -> Self {
+		Self { flags, ansi_enabled }
+	}
+
+	// Returns true if all the versions behaved the same
+	pub fn run(&self, code_path: &Path) -> bool {
+		match self.compare(code_path) {
+			Ok(same) => same,
+			Err(e) => {
+				Diagnostic::error("compare", e).emit();
+				false
+			}
+		}
+	}
+
+	fn compare(&self, code_path: &Path) -> Result<bool, String> {
+		let mut versions = vec![];
+		for version in &self.flags.compare_versions {
+			versions.push(Interpreter::resolve_version(version.clone())?);
+		}
+		if versions.len() < 2 {
+			return Err(String::from(
+				"At least two versions to compare are needed - use the --versions flag, for example --versions 0.3.0,0.4.0",
+			));
+		}
+		let input = self.read_input()?;
+		let seed = self.flags.seed.unwrap_or_else(rand::random);
+		let timeout = Duration::from_secs(self.flags.timeout.unwrap_or(DEFAULT_TIMEOUT));
+		let executable = std::env::current_exe().map_err(|e| format!("Couldn't find the interpreter executable: {}", e))?;
+		let code_path = code_path.canonicalize().map_err(|e| format!("Couldn't open {:?}: {}", code_path, e))?;
+		Diagnostic::info(format!("Comparing {} with the seed {}", versions.join(", "), seed)).emit();
+
+		let mut executions = vec![];
+		for version in versions {
+			let snapshot_path = std::env::temp_dir().join(format!("the-golden-compare-{}-{}.json", std::process::id(), version));
+			let mut arguments = vec![
+				String::from("run"),
+				code_path.to_string_lossy().to_string(),
+				String::from("--version"),
+				version.clone(),
+				String::from("--final-snapshot"),
+				snapshot_path.to_string_lossy().to_string(),
+			];
+			// All the versions get the same seed, so the random numbers don't make them differ
+			arguments.extend(Tester::forwarded_arguments(&Flags {
+				seed: Some(seed),
+				..self.flags.clone()
+			}));
+			let result = Tester::run_program(&executable, &arguments, input.clone(), timeout);
+			// Without the final state the code couldn't even be parsed by this version
+			let snapshot = Snapshot::load(&snapshot_path).ok();
+			std::fs::remove_file(&snapshot_path).ok();
+			let output = result.map_err(|e| format!("Version {}: {}", version, e))?.output;
+			executions.push(Execution { version, output, snapshot });
+		}
+
+		let mut same = true;
+		let mut memory_compared = true;
+		let baseline = &executions[0];
+		for execution in &executions[1..] {
+			if let Some(difference) = Self::output_difference(baseline, execution) {
+				same = false;
+				self.print_difference(baseline, execution, "output", &difference);
+			}
+			match (&baseline.snapshot, &execution.snapshot) {
+				(Some(expected), Some(actual)) => {
+					if let Some(difference) = Self::memory_difference(expected, actual) {
+						same = false;
+						self.print_difference(baseline, execution, "final memory", &difference);
+					}
+				}
+				_ => {
+					memory_compared = false;
+					for missing in [baseline, execution].iter().filter(|execution| execution.snapshot.is_none()) {
+						Diagnostic::warning("compare", format!("Version {} didn't run the code to the end, so its final memory can't be compared", missing.version)).emit();
+					}
+				}
+			}
+		}
+		if same {
+			Diagnostic::info(format!(
+				"All the versions printed the same output{}",
+				if memory_compared { " and ended with the same memory" } else { "" }
+			))
+			.emit();
+		}
+		Ok(same)
+	}
+
+	// The input from the --input file, or whatever is piped into the comparison
+	fn read_input(&self) -> Result<Vec<u8>, String> {
+		if let Some(path) = &self.flags.input_path {
+			return std::fs::read(path).map_err(|e| format!("Couldn't read the input file {:?}: {}", path, e));
+		}
+		let mut input = vec![];
+		if !std::io::stdin().is_terminal() {
+			std::io::stdin().read_to_end(&mut input).map_err(|e| format!("Couldn't read the input: {}", e))?;
+		}
+		Ok(input)
+	}
+
+	fn print_difference(&self, baseline: &Execution, execution: &Execution, what: &str, difference: &(String, String, String)) {
+		let (location, expected, actual) = difference;
+		println!(
+			"{}The {} of {} and {} differs first at {}",
+			Utils::ansi_escape_text("91", "DIVERGENCE", INFO_PREFIX_LENGTH, self.ansi_enabled),
+			what,
+			baseline.version,
+			execution.version,
+			location
+		);
+		println!("{}{}: {}", " ".repeat(INFO_PREFIX_LENGTH), baseline.version, expected);
+		println!("{}{}: {}", " ".repeat(INFO_PREFIX_LENGTH), execution.version, actual);
+	}
+
+	// The line and column of the first different character, with the line from both outputs
+	fn output_difference(baseline: &Execution, execution: &Execution) -> Option<(String, String, String)> {
+		let (expected, actual) = (String::from_utf8_lossy(&baseline.output), String::from_utf8_lossy(&execution.output));
+		let index = expected.char_indices().zip(actual.chars()).find(|((_, a), b)| a != b).map(|((index, _), _)| index);
+		let index = match index {
+			Some(index) => index,
+			None if expected.len() == actual.len() => return None,
+			None => expected.len().min(actual.len()),
+		};
+		let line_start = expected[..index].rfind('\n').map(|start| start + 1).unwrap_or(0);
+		let location = format!("{}:{}", expected[..index].matches('\n').count() + 1, expected[line_start..index].chars().count() + 1);
+		let line = |text: &str| match text[line_start..].split('\n').next() {
+			Some(line) if line_start < text.len() => format!("{:?}", line.trim_end_matches('\r')),
+			_ => String::from("<end of the output>"),
+		};
+		Some((format!("the line:column {} of the output", location), line(&expected), line(&actual)))
+	}
+
+	// The first memory row, pointer or cell that ended up different
+	fn memory_difference(expected: &Snapshot, actual: &Snapshot) -> Option<(String, String, String)> {
+		let rows = [
+			("global memory row 0", &expected.memory[0], &actual.memory[0]),
+			("global memory row 1", &expected.memory[1], &actual.memory[1]),
+			("local memory row 0", &expected.local_memory[0], &actual.local_memory[0]),
+			("local memory row 1", &expected.local_memory[1], &actual.local_memory[1]),
+		];
+		if expected.active_memory != actual.active_memory {
+			return Some((String::from("the active global memory row"), expected.active_memory.to_string(), actual.active_memory.to_string()));
+		}
+		if expected.active_local_memory != actual.active_local_memory {
+			return Some((
+				String::from("the active local memory row"),
+				expected.active_local_memory.to_string(),
+				actual.active_local_memory.to_string(),
+			));
+		}
+		for (name, expected, actual) in rows {
+			let (expected_cells, actual_cells) = (expected.cells(), actual.cells());
+			let mut indices = expected_cells.iter().chain(actual_cells.iter()).map(|(index, _)| *index).collect::<Vec<isize>>();
+			indices.sort();
+			indices.dedup();
+			let value = |cells: &[(isize, f64)], index: isize| cells.iter().find(|(cell, _)| *cell == index).map(|(_, value)| *value).unwrap_or(0.0);
+			for index in indices {
+				let (a, b) = (value(&expected_cells, index), value(&actual_cells, index));
+				if a != b && !(a.is_nan() && b.is_nan()) {
+					return Some((format!("the cell {} of the {}", index, name), a.to_string(), b.to_string()));
+				}
+			}
+			if expected.pointer() != actual.pointer() {
+				return Some((format!("the pointer of the {}", name), expected.pointer().to_string(), actual.pointer().to_string()));
+			}
+		}
+		None
+	}
+}
@@ -0,0 +1,230 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Serialize;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::EnvFilter;
+
+use crate::{Utils, INFO_PREFIX_LENGTH};
+
+static JSON_FORMAT: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+	Error,
+	Warning,
+	Info,
+	Debug,
+	// What the --debug-heavy flag adds
+	Trace,
+}
+
+impl Severity {
+	fn label(&self) -> (&'static str, &'static str) {
+		match self {
+			Self::Error => ("91", "ERROR"),
+			Self::Warning => ("93", "WARNING"),
+			Self::Info => ("92", "INFO"),
+			Self::Debug => ("94", "DEBUG"),
+			Self::Trace => ("34", "HEAVY DEBUG"),
+		}
+	}
+
+	fn from_level(level: &Level) -> Self {
+		match *level {
+			Level::ERROR => Self::Error,
+			Level::WARN => Self::Warning,
+			Level::INFO => Self::Info,
+			Level::DEBUG => Self::Debug,
+			Level::TRACE => Self::Trace,
+		}
+	}
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Span {
+	pub file: PathBuf,
+	pub line: usize,
+	pub column: usize,
+}
+
+// A message for the user - a tracing event on stderr, printed as a labelled line or as a JSON object with --message-format json
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+	pub severity: Severity,
+	// Identifies the kind of the problem for the tools reading the messages, debug events don't have one
+	pub code: Option<&'static str>,
+	pub message: String,
+	pub span: Option<Span>,
+}
+
+impl std::fmt::Display for Diagnostic {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.message)
+	}
+}
+
+impl Diagnostic {
+	// Sends the messages to stderr, so only the output of the code is on stdout
+	// RUST_LOG chooses which levels are printed, otherwise it's the info level, or debug and trace with the debug flags
+	pub fn init(json: bool, default_level: Level, ansi_enabled: bool) {
+		JSON_FORMAT.store(json, Ordering::SeqCst);
+		let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default_level.as_str()));
+		tracing_subscriber::fmt()
+			.with_env_filter(filter)
+			.with_writer(std::io::stderr)
+			.event_format(Formatter { json, ansi_enabled })
+			.init();
+	}
+
+	pub fn json() -> bool {
+		JSON_FORMAT.load(Ordering::SeqCst)
+	}
+
+	pub fn new(severity: Severity, code: Option<&'static str>, message: impl Into<String>) -> Self {
+		Self {
+			severity,
+			code,
+			message: message.into(),
+			span: None,
+		}
+	}
+
+	pub fn error(code: &'static str, message: impl Into<String>) -> Self {
+		Self::new(Severity::Error, Some(code), message)
+	}
+
+	pub fn warning(code: &'static str, message: impl Into<String>) -> Self {
+		Self::new(Severity::Warning, Some(code), message)
+	}
+
+	pub fn info(message: impl Into<String>) -> Self {
+		Self::new(Severity::Info, None, message)
+	}
+
+	pub fn debug(message: impl Into<String>) -> Self {
+		Self::new(Severity::Debug, None, message)
+	}
+
+	pub fn trace(message: impl Into<String>) -> Self {
+		Self::new(Severity::Trace, None, message)
+	}
+
+	pub fn at(mut self, file: &Path, line: usize, column: usize) -> Self {
+		self.span = Some(Span {
+			file: file.to_path_buf(),
+			line,
+			column,
+		});
+		self
+	}
+
+	pub fn emit(&self) {
+		let code = self.code;
+		let file = self.span.as_ref().map(|span| span.file.to_string_lossy().into_owned());
+		let file = file.as_deref();
+		let line = self.span.as_ref().map(|span| span.line as u64);
+		let column = self.span.as_ref().map(|span| span.column as u64);
+		let message = &self.message;
+		match self.severity {
+			Severity::Error => tracing::error!(code, file, line, column, "{}", message),
+			Severity::Warning => tracing::warn!(code, file, line, column, "{}", message),
+			Severity::Info => tracing::info!(code, file, line, column, "{}", message),
+			Severity::Debug => tracing::debug!(code, file, line, column, "{}", message),
+			Severity::Trace => tracing::trace!(code, file, line, column, "{}", message),
+		}
+	}
+}
+
+// The fields of a tracing event sent by Diagnostic::emit
+#[derive(Default)]
+struct Fields {
+	code: Option<String>,
+	message: String,
+	file: Option<String>,
+	line: Option<u64>,
+	column: Option<u64>,
+}
+
+impl Visit for Fields {
+	fn record_str(&mut self, field: &Field, value: &str) {
+		match field.name() {
+			"code" => self.code = Some(value.to_string()),
+			"file" => self.file = Some(value.to_string()),
+			"message" => self.message = value.to_string(),
+			_ => {}
+		}
+	}
+
+	fn record_u64(&mut self, field: &Field, value: u64) {
+		match field.name() {
+			"line" => self.line = Some(value),
+			"column" => self.column = Some(value),
+			_ => {}
+		}
+	}
+
+	fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+		if field.name() == "message" {
+			self.message = format!("{:?}", value);
+		}
+	}
+}
+
+// What --message-format json prints for each message
+#[derive(Serialize)]
+struct JsonMessage<'a> {
+	severity: Severity,
+	code: Option<&'a str>,
+	message: &'a str,
+	span: Option<Span>,
+}
+
+struct Formatter {
+	json: bool,
+	ansi_enabled: bool,
+}
+
+impl<S, N> FormatEvent<S, N> for Formatter
+where
+	S: Subscriber + for<'a> LookupSpan<'a>,
+	N: for<'a> FormatFields<'a> + 'static,
+{
+	fn format_event(&self, _context: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> std::fmt::Result {
+		let mut fields = Fields::default();
+		event.record(&mut fields);
+		let severity = Severity::from_level(event.metadata().level());
+		// New lines at the start separate the message from the output of the code, which doesn't have to end with one
+		let message = fields.message.trim_start_matches('\n');
+		if self.json {
+			let span = match (&fields.file, fields.line, fields.column) {
+				(Some(file), Some(line), Some(column)) => Some(Span {
+					file: PathBuf::from(file),
+					line: line as usize,
+					column: column as usize,
+				}),
+				_ => None,
+			};
+			let json_message = JsonMessage {
+				severity,
+				code: fields.code.as_deref(),
+				message,
+				span,
+			};
+			return writeln!(writer, "{}", serde_json::to_string(&json_message).unwrap());
+		}
+		let (style, label) = severity.label();
+		writeln!(
+			writer,
+			"{}{}{}",
+			"\n".repeat(fields.message.len() - message.len()),
+			Utils::ansi_escape_text(style, label, INFO_PREFIX_LENGTH, self.ansi_enabled),
+			message
+		)
+	}
+}
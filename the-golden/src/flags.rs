@@ -1,22 +1,303 @@
-use crate::Utils;
+use crate::{Utils, Warning, WarningLevel, Warnings, WARNINGS};
 
-#[derive(Clone, Debug)]
-pub struct Warnings {
-	pub too_left_pointer: bool,
+// The commands that run code and the flags changing how it runs
+const EXECUTING: &[&str] = &["run", "test", "compare", "repl"];
+// The commands that run code or only check it, and the flags changing how it is preprocessed
+const PREPROCESSING: &[&str] = &["run", "test", "compare", "check", "repl"];
+// The flags whose values add up instead of replacing each other
+const REPEATABLE: &[&str] = &["--define", "--allow", "--warn", "--deny"];
+
+struct Command {
+	name: &'static str,
+	arguments: &'static str,
+	description: &'static str,
+}
+
+const COMMANDS: [Command; 9] = [
+	Command {
+		name: "run",
+		arguments: "[path]",
+		description: "Run the code from the file (the default command)",
+	},
+	Command {
+		name: "repl",
+		arguments: "",
+		description: "Run the code typed line by line, each line continues with the memory the previous ones left",
+	},
+	Command {
+		name: "check",
+		arguments: "[path]",
+		description: "Check that the code in the file (or in all the .au files in the directory) is valid without running it",
+	},
+	Command {
+		name: "test",
+		arguments: "[directory]",
+		description: "Run the programs with expected outputs in the directory and check what they print",
+	},
+	Command {
+		name: "migrate",
+		arguments: "<path>",
+		description: "Rewrite the code written for one version into the syntax of a newer one",
+	},
+	Command {
+		name: "fmt",
+		arguments: "<path>",
+		description: "Indent the loops and functions of the code and remove the whitespace at the ends of the lines",
+	},
+	Command {
+		name: "compare",
+		arguments: "<path>",
+		description: "Run the code under several versions and report where they behave differently",
+	},
+	Command {
+		name: "versions",
+		arguments: "",
+		description: "List the versions of the interpreter and what changed in them",
+	},
+	Command {
+		name: "help",
+		arguments: "[command]",
+		description: "Print the help for all the commands or for one of them",
+	},
+];
+
+struct Flag {
+	name: &'static str,
+	value: Option<&'static str>,
+	description: &'static str,
+	commands: &'static [&'static str],
+}
+
+const FLAGS: [Flag; 30] = [
+	Flag {
+		name: "-",
+		value: Some("<code>"),
+		description: "Run the code given right in the command line instead of a file",
+		commands: &["run"],
+	},
+	Flag {
+		name: "--allow",
+		value: Some("<warning>"),
+		description: "Don't print the warning, or any of them with `all` (can be used multiple times)",
+		commands: EXECUTING,
+	},
+	Flag {
+		name: "--assertions",
+		value: None,
+		description: "Check the #assert statements while running the code",
+		commands: PREPROCESSING,
+	},
+	Flag {
+		name: "--color",
+		value: Some("<auto|always|never>"),
+		description: "Color the messages and reports (`auto` colors them only in a terminal and without NO_COLOR set)",
+		commands: &[],
+	},
+	Flag {
+		name: "--debug",
+		value: None,
+		description: "Print the parsed commands and the memory at the end of the execution",
+		commands: &["run"],
+	},
+	Flag {
+		name: "--debug-heavy",
+		value: None,
+		description: "Print everything --debug does and the memory after each command",
+		commands: &["run"],
+	},
+	Flag {
+		name: "--define",
+		value: Some("<name[=value]>"),
+		description: "Define a symbol for the #if and #elif statements (can be used multiple times)",
+		commands: PREPROCESSING,
+	},
+	Flag {
+		name: "--deny",
+		value: Some("<warning>"),
+		description: "Stop the code with an error instead of printing the warning (can be used multiple times)",
+		commands: EXECUTING,
+	},
+	Flag {
+		name: "--disable-too-left-pointer-warning",
+		value: None,
+		description: "Disable the warning fired when you go to the -1 index in memory",
+		commands: EXECUTING,
+	},
+	Flag {
+		name: "--disable-warnings",
+		value: None,
+		description: "Disable all warnings (the same as --allow all)",
+		commands: EXECUTING,
+	},
+	Flag {
+		name: "--final-snapshot",
+		value: Some("<path>"),
+		description: "Save the interpreter state to the file when the execution ends",
+		commands: &["run"],
+	},
+	Flag {
+		name: "--from",
+		value: Some("<version>"),
+		description: "The version the code was written for (taken from its #version statement by default)",
+		commands: &["migrate"],
+	},
+	Flag {
+		name: "--help",
+		value: None,
+		description: "Print the help (also -h)",
+		commands: &[],
+	},
+	Flag {
+		name: "--hide-console",
+		value: None,
+		description: "Hide the console when running the code",
+		commands: &["run"],
+	},
+	Flag {
+		name: "--input",
+		value: Some("<path>"),
+		description: "The file to use as the input of the code (the piped input by default)",
+		commands: &["run", "compare"],
+	},
+	Flag {
+		name: "--message-format",
+		value: Some("<text|json>"),
+		description: "Print the errors, warnings and debug messages as text or as JSON objects",
+		commands: &["run", "check", "test", "migrate", "compare", "fmt", "repl"],
+	},
+	Flag {
+		name: "--no-brainfuck",
+		value: None,
+		description: "Set the first cells of the secondary memories to 0",
+		commands: EXECUTING,
+	},
+	Flag {
+		name: "--non-finite",
+		value: Some("<error|saturate|number>"),
+		description: "What an arithmetic command making the cell NaN or infinite does - stop the code, clamp it to the largest finite number (NaN to 0) or replace it with the number",
+		commands: EXECUTING,
+	},
+	Flag {
+		name: "--output",
+		value: Some("<path>"),
+		description: "Write the migrated or formatted code to the file instead of printing it (it can be the original file to replace it)",
+		commands: &["migrate", "fmt"],
+	},
+	Flag {
+		name: "--resume",
+		value: Some("<path>"),
+		description: "Continue running the program saved in the snapshot file",
+		commands: &["run"],
+	},
+	Flag {
+		name: "--sebek",
+		value: Some("<a|b|c>"),
+		description: "The results of dividing a number < 0, 0 itself and a number > 0 by 0",
+		commands: EXECUTING,
+	},
+	Flag {
+		name: "--seed",
+		value: Some("<number>"),
+		description: "Seed the random number generator",
+		commands: EXECUTING,
+	},
+	Flag {
+		name: "--snapshot",
+		value: Some("<path>"),
+		description: "Save the interpreter state to the file when the execution is interrupted or the step limit is reached",
+		commands: &["run"],
+	},
+	Flag {
+		name: "--step-limit",
+		value: Some("<number>"),
+		description: "Stop the execution after the given number of commands",
+		commands: EXECUTING,
+	},
+	Flag {
+		name: "--strict-preprocessor",
+		value: None,
+		description: "Treat problems with the preprocessor statements as errors and don't run the code",
+		commands: PREPROCESSING,
+	},
+	Flag {
+		name: "--timeout",
+		value: Some("<seconds>"),
+		description: "Stop each program after the given number of seconds (30 by default)",
+		commands: &["test", "compare"],
+	},
+	Flag {
+		name: "--to",
+		value: Some("<version>"),
+		description: "The version to migrate the code to (the latest one by default)",
+		commands: &["migrate"],
+	},
+	Flag {
+		name: "--version",
+		value: Some("<version>"),
+		description: "The version of the interpreter to use, or a requirement like ^0.3",
+		commands: &["run", "test", "check", "fmt", "repl"],
+	},
+	Flag {
+		name: "--versions",
+		value: Some("<versions>"),
+		description: "The versions to compare, separated by commas",
+		commands: &["compare"],
+	},
+	Flag {
+		name: "--warn",
+		value: Some("<warning>"),
+		description: "Print the warning even if a # statement disables or denies it (can be used multiple times)",
+		commands: EXECUTING,
+	},
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorMode {
+	Auto,
+	Always,
+	Never,
+}
+
+// What happens to NaN and infinite results of the arithmetic commands, they are kept without one
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NonFinitePolicy {
+	Error,
+	Saturate,
+	Replace(f64),
 }
 
 #[derive(Clone, Debug)]
 pub struct Flags {
-	pub disabled_warnings: Warnings,
+	// Only the levels set by the flags, the # statements of the code are added to them when it is preprocessed
+	pub warnings: Warnings,
 
 	pub action: Option<String>,
+	pub assertions: bool,
 	pub code_path: Option<std::path::PathBuf>,
+	pub color: ColorMode,
+	pub compare_versions: Vec<String>,
+	pub migrate_from: Option<String>,
+	pub migrate_to: Option<String>,
+	pub output_path: Option<std::path::PathBuf>,
 	pub debug: bool,
 	pub debug_heavy: bool,
+	pub defines: std::collections::HashMap<String, String>,
+	pub final_snapshot_path: Option<std::path::PathBuf>,
+	pub help: bool,
+	pub help_command: Option<String>,
+	pub input_path: Option<std::path::PathBuf>,
+	pub json_messages: bool,
 	pub no_brainfuck: bool,
 	pub no_console: bool,
+	pub non_finite: Option<NonFinitePolicy>,
 	pub raw_code_to_run: Option<String>,
+	pub resume: Option<std::path::PathBuf>,
 	pub sebek: [Option<f64>; 3],
+	pub seed: Option<u64>,
+	pub snapshot_path: Option<std::path::PathBuf>,
+	pub step_limit: Option<u64>,
+	pub timeout: Option<u64>,
+	pub strict_preprocessor: bool,
 	pub version: Option<String>,
 }
 
@@ -29,75 +310,304 @@ impl Default for Flags {
 impl Flags {
 	pub fn new() -> Self {
 		Self {
-			disabled_warnings: Warnings { too_left_pointer: false },
+			warnings: Warnings::new(),
 
 			action: None,
+			assertions: false,
 			code_path: None,
+			color: ColorMode::Auto,
+			compare_versions: vec![],
+			migrate_from: None,
+			migrate_to: None,
+			output_path: None,
 			debug: false,
 			debug_heavy: false,
+			defines: std::collections::HashMap::new(),
+			final_snapshot_path: None,
+			help: false,
+			help_command: None,
+			input_path: None,
+			json_messages: false,
 			no_brainfuck: false,
 			no_console: false,
+			non_finite: None,
 			raw_code_to_run: None,
+			resume: None,
 			sebek: [None, None, None],
+			seed: None,
+			snapshot_path: None,
+			step_limit: None,
+			timeout: None,
+			strict_preprocessor: false,
 			version: None,
 		}
 	}
 
-	pub fn parse(&mut self, args: &[String]) {
-		let args_count = args.len();
-		let mut i = 0;
-		while i < args_count {
+	pub fn parse(&mut self, args: &[String]) -> Result<(), String> {
+		let mut used_flags: Vec<&Flag> = vec![];
+		let mut positional = vec![];
+		let mut i = 1;
+		while i < args.len() {
 			let argument = &args[i];
-			let argument_lowercase = argument.to_lowercase();
-			match argument_lowercase.as_str() {
-				"--debug" => self.debug = true,
-				"--debug-heavy" => {
-					self.debug = true;
-					self.debug_heavy = true;
+			let argument_lowercase = if argument == "-h" { String::from("--help") } else { argument.to_lowercase() };
+			if argument.starts_with('-') {
+				let Some(flag) = FLAGS.iter().find(|flag| flag.name == argument_lowercase) else {
+					let names = FLAGS.iter().map(|flag| flag.name).collect::<Vec<&str>>();
+					return Err(match Utils::closest_match(&argument_lowercase, &names) {
+						Some(suggestion) => format!("Unknown flag `{}`, did you mean `{}`?", argument, suggestion),
+						None => format!("Unknown flag `{}` (see `the-golden help` for the list of flags)", argument),
+					});
+				};
+				// Only the flags collecting their values can be given more than once, otherwise it's unclear which value wins
+				if flag.value.is_some() && !REPEATABLE.contains(&flag.name) && used_flags.iter().any(|used| used.name == flag.name) {
+					return Err(format!("The flag `{}` can only be given once", flag.name));
 				}
-				"--hide-console" => self.no_console = true,
-				"--version" => {
-					if self.version.is_none() && i + 1 < args_count {
-						self.version = Some(args[i + 1].clone());
+				let value = match flag.value {
+					Some(value_name) => {
+						i += 1;
+						match args.get(i) {
+							Some(value) => value.as_str(),
+							None => return Err(format!("The flag `{}` needs a value: {} {}", flag.name, flag.name, value_name)),
+						}
 					}
-				}
-				"--disable-warnings" => self.disabled_warnings = Warnings { too_left_pointer: true },
-				"--disable-too-left-pointer-warning" => self.disabled_warnings.too_left_pointer = true,
-				"--no-brainfuck" => self.no_brainfuck = true,
-				"--sebek" => {
-					if i + 1 < args_count {
-						self.sebek = Utils::parse_sebek(&args[i + 1]);
+					None => "",
+				};
+				self.apply(flag.name, value)?;
+				used_flags.push(flag);
+			} else if self.action.is_none() && positional.is_empty() && COMMANDS.iter().any(|command| command.name == argument_lowercase) {
+				self.action = Some(argument_lowercase);
+			} else {
+				positional.push(argument.clone());
+			}
+			i += 1;
+		}
+
+		let action = match self.action.clone() {
+			Some(action) => action,
+			None => {
+				// Without a command the code is run, unless the first argument looks like a misspelled command
+				if let Some(first) = positional.first().filter(|first| !std::path::Path::new(first).exists()) {
+					let names = COMMANDS.iter().map(|command| command.name).collect::<Vec<&str>>();
+					if let Some(suggestion) = Utils::closest_match(first, &names) {
+						return Err(format!("Unknown command `{}`, did you mean `{}`?", first, suggestion));
 					}
 				}
-				"-" => {
-					if self.raw_code_to_run.is_none() && i + 1 < args_count {
-						self.raw_code_to_run = Some(args[i + 1].clone());
+				String::from("run")
+			}
+		};
+		self.action = Some(action.clone());
+		if let Some(flag) = used_flags.iter().find(|flag| !flag.commands.is_empty() && !flag.commands.contains(&action.as_str())) {
+			return Err(format!("The flag `{}` can't be used with the `{}` command (only with `{}`)", flag.name, action, flag.commands.join("`, `")));
+		}
+		if self.help {
+			return Ok(());
+		}
+		if self.resume.is_some() && self.version.is_some() {
+			return Err(String::from("The flag `--version` can't be used with `--resume`, the code continues with the version it was saved by"));
+		}
+
+		let command = COMMANDS.iter().find(|command| command.name == action).unwrap();
+		if positional.len() > 1 || (positional.len() == 1 && command.arguments.is_empty()) {
+			return Err(format!("Unexpected argument `{}` for the `{}` command", positional[positional.len().min(2) - 1], action));
+		}
+		let argument = positional.pop();
+		if argument.is_none() && command.arguments.starts_with('<') {
+			return Err(format!("The `{}` command needs the {} argument: the-golden {} {}", action, command.arguments, action, command.arguments));
+		}
+		match action.as_str() {
+			"run" => self.code_path = argument.map(|path| Self::run_path(&path)),
+			"check" | "test" => self.code_path = Some(std::path::PathBuf::from(argument.unwrap_or_else(|| String::from(".")))),
+			"help" => {
+				if let Some(name) = &argument {
+					if !COMMANDS.iter().any(|command| command.name == name.to_lowercase()) {
+						let names = COMMANDS.iter().map(|command| command.name).collect::<Vec<&str>>();
+						return Err(match Utils::closest_match(name, &names) {
+							Some(suggestion) => format!("Unknown command `{}`, did you mean `{}`?", name, suggestion),
+							None => format!("Unknown command `{}`", name),
+						});
 					}
 				}
-				"run" => {
-					if self.action.is_none() {
-						self.action = Some(String::from("run"));
-						if self.code_path.is_none() && i + 1 < args_count && !args[i + 1].starts_with('-') {
-							let mut path = if args[i + 1] != "." && (args[i + 1].starts_with('.') || args[i + 1].starts_with('/')) {
-								let mut p = std::path::PathBuf::from(args[0].clone());
-								p.pop();
-								p.push(args[i + 1].clone());
-								p
-							} else {
-								std::path::PathBuf::from(args[i + 1].clone())
-							};
-							if path.is_file() {
-								path.set_file_name("maumivu.au");
-							} else {
-								path.push("maumivu.au");
-							}
-							self.code_path = Some(path);
-						}
-					}
+				self.help = true;
+				self.help_command = argument.map(|name| name.to_lowercase());
+			}
+			_ => self.code_path = argument.map(std::path::PathBuf::from),
+		}
+		Ok(())
+	}
+
+	fn apply(&mut self, flag: &str, value: &str) -> Result<(), String> {
+		let number = |value: &str| value.parse::<u64>().map_err(|_| format!("The flag `{}` needs a whole number, not `{}`", flag, value));
+		match flag {
+			"-" => self.raw_code_to_run = Some(value.to_string()),
+			"--assertions" => self.assertions = true,
+			"--color" => {
+				self.color = match value.to_lowercase().as_str() {
+					"auto" => ColorMode::Auto,
+					"always" => ColorMode::Always,
+					"never" => ColorMode::Never,
+					_ => return Err(format!("The flag `{}` needs `auto`, `always` or `never`, not `{}`", flag, value)),
 				}
-				_ => {}
 			}
-			i += 1;
+			"--debug" => self.debug = true,
+			"--debug-heavy" => {
+				self.debug = true;
+				self.debug_heavy = true;
+			}
+			"--define" => {
+				let (name, value) = value.split_once('=').unwrap_or((value, ""));
+				self.defines.insert(name.to_string(), value.to_string());
+			}
+			"--allow" | "--warn" | "--deny" => {
+				let level = match flag {
+					"--allow" => WarningLevel::Allow,
+					"--warn" => WarningLevel::Warn,
+					_ => WarningLevel::Deny,
+				};
+				let warnings = Warning::parse(value).map_err(|suggestion| match suggestion {
+					Some(suggestion) => format!("Unknown warning `{}`, did you mean `{}`?", value, suggestion),
+					None => format!("Unknown warning `{}` (see `the-golden help run` for the list of warnings)", value),
+				})?;
+				for warning in warnings {
+					self.warnings.set(warning, level);
+				}
+			}
+			"--disable-warnings" => {
+				for (warning, _, _) in WARNINGS {
+					self.warnings.set(warning, WarningLevel::Allow);
+				}
+			}
+			"--disable-too-left-pointer-warning" => self.warnings.set(Warning::TooLeftPointer, WarningLevel::Allow),
+			"--final-snapshot" => self.final_snapshot_path = Some(std::path::PathBuf::from(value)),
+			"--from" => self.migrate_from = Some(value.to_string()),
+			"--help" => self.help = true,
+			"--hide-console" => self.no_console = true,
+			"--input" => self.input_path = Some(std::path::PathBuf::from(value)),
+			"--message-format" => {
+				self.json_messages = match value.to_lowercase().as_str() {
+					"text" => false,
+					"json" => true,
+					_ => return Err(format!("The flag `{}` needs `text` or `json`, not `{}`", flag, value)),
+				}
+			}
+			"--no-brainfuck" => self.no_brainfuck = true,
+			"--non-finite" => self.non_finite = Some(Utils::parse_non_finite(value)?),
+			"--output" => self.output_path = Some(std::path::PathBuf::from(value)),
+			"--resume" => self.resume = Some(std::path::PathBuf::from(value)),
+			"--sebek" => self.sebek = Utils::parse_sebek(value).map_err(|e| format!("The flag `{}` needs up to 3 numbers separated by `|`: {}", flag, e))?,
+			"--seed" => self.seed = Some(number(value)?),
+			"--snapshot" => self.snapshot_path = Some(std::path::PathBuf::from(value)),
+			"--step-limit" => self.step_limit = Some(number(value)?),
+			"--timeout" => self.timeout = Some(number(value)?),
+			"--strict-preprocessor" => self.strict_preprocessor = true,
+			"--to" => self.migrate_to = Some(value.to_string()),
+			"--version" => self.version = Some(value.to_string()),
+			"--versions" => {
+				self.compare_versions = value.split(',').map(|version| version.trim().to_string()).filter(|version| !version.is_empty()).collect();
+			}
+			_ => {}
+		}
+		Ok(())
+	}
+
+	// Paths are relative to the working directory, and a directory means its maumivu.au file
+	fn run_path(argument: &str) -> std::path::PathBuf {
+		let mut path = std::path::PathBuf::from(argument);
+		if path.is_dir() {
+			path.push("maumivu.au");
+		}
+		path
+	}
+
+	// The usage of all the commands, or of one command with the flags it accepts
+	pub fn help(command: Option<&str>) -> String {
+		let mut text = String::new();
+		let flags = FLAGS.iter().filter(|flag| command.is_none_or(|command| flag.commands.is_empty() || flag.commands.contains(&command))).collect::<Vec<&Flag>>();
+		match COMMANDS.iter().find(|candidate| Some(candidate.name) == command) {
+			Some(command) => text.push_str(&format!("Usage: the-golden {} {}[flags]\n\n{}\n", command.name, if command.arguments.is_empty() { String::new() } else { format!("{} ", command.arguments) }, command.description)),
+			None => {
+				text.push_str("Usage: the-golden [command] [arguments] [flags]\n\nCommands:\n");
+				let width = COMMANDS.iter().map(|command| command.name.len() + command.arguments.len() + 1).max().unwrap_or(0);
+				for command in &COMMANDS {
+					text.push_str(&format!("  {:width$}  {}\n", format!("{} {}", command.name, command.arguments), command.description, width = width));
+				}
+			}
+		}
+		text.push_str("\nFlags:\n");
+		let usage = |flag: &Flag| format!("{} {}", flag.name, flag.value.unwrap_or_default());
+		let width = flags.iter().map(|flag| usage(flag).len()).max().unwrap_or(0);
+		for flag in flags {
+			text.push_str(&format!("  {:width$}  {}\n", usage(flag), flag.description, width = width));
+		}
+		if command.is_none_or(|command| EXECUTING.contains(&command)) {
+			text.push_str("\nWarnings (for --allow, --warn, --deny and the #disable-warnings and #deny statements):\n");
+			let width = WARNINGS.iter().map(|(_, name, _)| name.len()).max().unwrap_or(0);
+			for (_, name, description) in WARNINGS {
+				text.push_str(&format!("  {:width$}  {}\n", name, description, width = width));
+			}
+		}
+		if command.is_none() {
+			text.push_str("\nRun `the-golden help <command>` to see only the flags of the command.\n");
 		}
+		text
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Flags;
+
+	fn parse(args: &str) -> Result<Flags, String> {
+		let mut flags = Flags::new();
+		let args = std::iter::once("the-golden").chain(args.split_whitespace()).map(String::from).collect::<Vec<String>>();
+		flags.parse(&args).map(|_| flags)
+	}
+
+	#[test]
+	fn commands_and_flags_are_read() {
+		let flags = parse("check examples --version 0.4.0 --define DEBUG --define LEVEL=2").unwrap();
+		assert_eq!(flags.action.as_deref(), Some("check"));
+		assert_eq!(flags.code_path, Some(std::path::PathBuf::from("examples")));
+		assert_eq!(flags.version.as_deref(), Some("0.4.0"));
+		assert_eq!(flags.defines.get("LEVEL").map(String::as_str), Some("2"));
+		// Without a command the code is run
+		assert_eq!(parse("--sebek 1|2|3").unwrap().action.as_deref(), Some("run"));
+	}
+
+	#[test]
+	fn misspelled_names_get_suggestions() {
+		assert_eq!(parse("--debgu").unwrap_err(), "Unknown flag `--debgu`, did you mean `--debug`?");
+		assert!(parse("--something-else").unwrap_err().starts_with("Unknown flag `--something-else` (see"));
+		assert_eq!(parse("chek").unwrap_err(), "Unknown command `chek`, did you mean `check`?");
+		assert_eq!(parse("help migrat").unwrap_err(), "Unknown command `migrat`, did you mean `migrate`?");
+		assert_eq!(parse("--allow too-left-pionter").unwrap_err(), "Unknown warning `too-left-pionter`, did you mean `too-left-pointer`?");
+	}
+
+	#[test]
+	fn values_are_checked() {
+		assert_eq!(parse("run --sebek").unwrap_err(), "The flag `--sebek` needs a value: --sebek <a|b|c>");
+		assert!(parse("--sebek 1|x").unwrap_err().starts_with("The flag `--sebek` needs up to 3 numbers"));
+		assert_eq!(parse("--step-limit many").unwrap_err(), "The flag `--step-limit` needs a whole number, not `many`");
+		assert_eq!(parse("--color sometimes").unwrap_err(), "The flag `--color` needs `auto`, `always` or `never`, not `sometimes`");
+	}
+
+	#[test]
+	fn flags_must_fit_the_command() {
+		assert_eq!(parse("--seed 1 --seed 2").unwrap_err(), "The flag `--seed` can only be given once");
+		assert!(parse("--allow all --deny nan-produced").is_ok());
+		assert_eq!(
+			parse("check --seed 1").unwrap_err(),
+			"The flag `--seed` can't be used with the `check` command (only with `run`, `test`, `compare`, `repl`)"
+		);
+		let error = parse("--resume state.json --version 0.5.0").unwrap_err();
+		assert!(error.starts_with("The flag `--version` can't be used with `--resume`"));
+	}
+
+	#[test]
+	fn arguments_are_counted() {
+		assert_eq!(parse("migrate").unwrap_err(), "The `migrate` command needs the <path> argument: the-golden migrate <path>");
+		assert_eq!(parse("versions 0.4.0").unwrap_err(), "Unexpected argument `0.4.0` for the `versions` command");
+		assert_eq!(parse("fmt a.au b.au").unwrap_err(), "Unexpected argument `b.au` for the `fmt` command");
+		// The help doesn't need the arguments of the command
+		assert!(parse("migrate --help").unwrap().help);
 	}
 }
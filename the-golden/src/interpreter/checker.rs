@@ -0,0 +1,149 @@
+use std::path::{Path, PathBuf};
+
+use crate::interpreter::preprocessor::Preprocessor;
+use crate::interpreter::project::Project;
+use crate::interpreter::versions_handler::frontend::{BracketsMatcher, Lexer, Parser, Validator};
+use crate::interpreter::versions_handler::Handler;
+use crate::interpreter::Interpreter;
+use crate::{Diagnostic, Flags, Severity, Utils, INFO_PREFIX_LENGTH};
+
+// What checking one file found - errors make the check fail, warnings don't
+#[derive(Clone, Debug, Default)]
+struct Report {
+	errors: Vec<Diagnostic>,
+	warnings: Vec<Diagnostic>,
+}
+
+pub struct Checker {
+	handler: Handler,
+	flags: Flags,
+	ansi_enabled: bool,
+}
+
+impl Checker {
+	pub fn new(flags: Flags, ansi_enabled: bool) -> Self {
+		Self {
+			handler: Handler::new(),
+			flags,
+			ansi_enabled,
+		}
+	}
+
+	// Returns true if none of the files has errors
+	pub fn run(&self, path: &Path) -> bool {
+		let mut files = vec![];
+		if path.is_dir() {
+			if let Err(e) = Self::find_files(path, &mut files) {
+				Diagnostic::error("io", format!("Couldn't read the files from {:?}: {}", path, e)).emit();
+				return false;
+			}
+			files.sort();
+		} else {
+			files.push(path.to_path_buf());
+		}
+		if files.is_empty() {
+			Diagnostic::warning("check", format!("No .au files found in {:?}", path)).emit();
+			return true;
+		}
+
+		let mut failed = 0;
+		for file in &files {
+			let report = self.check_file(file);
+			let name = file.strip_prefix(path).ok().filter(|name| !name.as_os_str().is_empty()).unwrap_or(file).display();
+			for warning in &report.warnings {
+				warning.emit();
+			}
+			if report.errors.is_empty() {
+				println!("{}{}", Utils::ansi_escape_text("92", "PASS", INFO_PREFIX_LENGTH, self.ansi_enabled), name);
+			} else {
+				failed += 1;
+				println!("{}{}", Utils::ansi_escape_text("91", "FAIL", INFO_PREFIX_LENGTH, self.ansi_enabled), name);
+				for error in &report.errors {
+					if Diagnostic::json() {
+						error.emit();
+					} else {
+						println!("{}{}", " ".repeat(INFO_PREFIX_LENGTH), error);
+					}
+				}
+			}
+		}
+		println!("{} file(s) valid, {} with errors", files.len() - failed, failed);
+		failed == 0
+	}
+
+	fn find_files(directory: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+		for entry in std::fs::read_dir(directory)? {
+			let path = entry?.path();
+			if path.is_dir() {
+				Self::find_files(&path, files)?;
+			} else if path.extension().is_some_and(|extension| extension == "au") {
+				files.push(path);
+			}
+		}
+		Ok(())
+	}
+
+	// Everything the interpreter does before running the code - preprocessing, choosing the version, lexing, parsing and matching the brackets
+	fn check_file(&self, path: &Path) -> Report {
+		let mut report = Report::default();
+		let code = match std::fs::read_to_string(path) {
+			Ok(code) => code,
+			Err(e) => {
+				report.errors.push(Diagnostic::error("io", format!("Couldn't open {:?}: {}", path, e)));
+				return report;
+			}
+		};
+		let project = match Project::load(path) {
+			Ok(project) => project,
+			Err(e) => {
+				report.errors.push(Diagnostic::error("project", e));
+				return report;
+			}
+		};
+		let mut preprocessor = Preprocessor::new(self.flags.clone(), self.flags.version.clone(), project.version.clone());
+		let (code, source_map) = match preprocessor.run(&code, path) {
+			Ok(result) => result,
+			Err(e) => {
+				report.errors.push(Diagnostic::error("preprocessor", e));
+				return report;
+			}
+		};
+		if self.flags.strict_preprocessor {
+			report.errors.extend(preprocessor.diagnostics.iter().map(|diagnostic| diagnostic.to_diagnostic(Severity::Error)));
+		} else {
+			report.warnings.extend(preprocessor.diagnostics.iter().map(|diagnostic| diagnostic.to_diagnostic(Severity::Warning)));
+		}
+		let requirement = self.flags.version.clone().or(preprocessor.version).or(project.version).unwrap_or_else(|| String::from("latest"));
+		let version = match self.handler.resolve_version(requirement) {
+			Ok(version) => version,
+			Err(e) => {
+				report.errors.push(Diagnostic::error("version", e));
+				return report;
+			}
+		};
+		let warnings = project.warnings.merged(&preprocessor.warnings).merged(&self.flags.warnings);
+		for message in Interpreter::unsupported_warnings(&warnings, &version) {
+			if self.flags.strict_preprocessor {
+				report.errors.push(Diagnostic::error("preprocessor", message));
+			} else {
+				report.warnings.push(Diagnostic::warning("preprocessor", message));
+			}
+		}
+
+		let lexer = Lexer::new(code, self.handler.grammar(&version).unwrap(), source_map);
+		// All the problems are collected, so they can be fixed at once
+		report.errors.extend(Validator::run_all(lexer.clone()));
+		let mut parser = Parser::new();
+		parser.run_skipping_errors(lexer);
+		let mut brackets_matcher = BracketsMatcher::new();
+		brackets_matcher.match_brackets(&parser.commands);
+		for i in brackets_matcher.unmatched(&parser.commands) {
+			let (command, line, column, file_path) = &parser.commands_info[i];
+			report
+				.errors
+				.push(Diagnostic::error("unmatched-bracket", format!("`{}` without a matching bracket {}", command, parser.location(i))).at(file_path, *line, *column));
+		}
+		report.errors.extend(self.handler.check(&version, &parser));
+		report
+	}
+}
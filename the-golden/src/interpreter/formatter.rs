@@ -0,0 +1,101 @@
+use std::path::Path;
+
+use crate::interpreter::source_map::SourceMap;
+use crate::interpreter::versions_handler::frontend::Lexer;
+use crate::interpreter::versions_handler::Handler;
+use crate::interpreter::{Migrator, Project};
+
+pub struct Formatter {
+	handler: Handler,
+	pub version: String,
+}
+
+impl Formatter {
+	// Without `version`, the version is taken from the #version statement of the code or from the golden.toml file next to it
+	pub fn new(version: Option<String>, code: &str, code_path: &Path) -> Result<Self, String> {
+		let handler = Handler::new();
+		let version = match version.or_else(|| Migrator::find_version(code)) {
+			Some(version) => version,
+			None => Project::load(code_path)?.version.unwrap_or_else(|| String::from("latest")),
+		};
+		let version = handler.resolve_version(version)?;
+		Ok(Self { handler, version })
+	}
+
+	// Indents the lines by the loops and functions they are in and removes the whitespace at their ends, the commands stay as they are
+	pub fn format(&self, code: &str, code_path: &Path) -> Result<String, String> {
+		let grammar = self.handler.grammar(&self.version).unwrap();
+		let (shebang, code) = Lexer::split_shebang(code);
+		let (line, column) = SourceMap::advance(shebang, 1, 1);
+		let mut source_map = SourceMap::new();
+		source_map.push(0, code_path.to_path_buf(), line, column);
+		let mut lexer = Lexer::new(code.to_string(), grammar, source_map);
+
+		let mut output = shebang.replace("\r\n", "\n");
+		let mut depth: usize = 0;
+		let mut line_start = true;
+		// The whitespace is only written once it is clear it isn't at the end of a line
+		let mut whitespace = String::new();
+		while let Some((token, ..)) = lexer.next()? {
+			let token = token.replace("\r\n", "\n");
+			if token.trim().is_empty() && !token.contains('\n') {
+				if !line_start {
+					whitespace.push_str(&token);
+				}
+				continue;
+			}
+			// Only a new line is left after the whitespace, the statements ending with one are indented like the commands
+			let blank = token.trim().is_empty();
+			let command = token.trim_start_matches('\'');
+			let closes = matches!(command, "]" | "@]" | "}");
+			if line_start && !blank {
+				output.push_str(&"\t".repeat(depth.saturating_sub(closes as usize)));
+			} else if !line_start && !blank {
+				output.push_str(&whitespace);
+			}
+			whitespace.clear();
+			output.push_str(&token);
+			line_start = token.ends_with('\n');
+			if command.starts_with('[') || command.ends_with('{') {
+				depth += 1;
+			} else if closes {
+				depth = depth.saturating_sub(1);
+			}
+		}
+		Ok(output)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Formatter;
+	use std::path::Path;
+
+	fn format(version: &str, code: &str) -> String {
+		let path = Path::new("maumivu.au");
+		Formatter::new(Some(version.to_string()), code, path).unwrap().format(code, path).unwrap()
+	}
+
+	#[test]
+	fn blocks_are_indented() {
+		assert_eq!(format("0.6.0", "|1|{\n[\n~\n]\n}\n|1|%\n"), "|1|{\n\t[\n\t\t~\n\t]\n}\n|1|%\n");
+		assert_eq!(format("0.6.0", "  !  [@ ~\n    $. @]\n"), "!  [@ ~\n\t$. @]\n");
+	}
+
+	#[test]
+	fn whitespace_at_the_ends_of_lines_is_removed() {
+		assert_eq!(format("0.6.0", "! \t\r\n\"a comment\" \n!  "), "!\n\"a comment\"\n!");
+	}
+
+	#[test]
+	fn old_line_ends_are_kept() {
+		assert_eq!(format("0.3.0", "'[ :\n'~:\n']:"), "'[ :\n\t'~:\n']:");
+	}
+
+	#[test]
+	fn the_version_comes_from_the_code() {
+		let path = Path::new("maumivu.au");
+		assert_eq!(Formatter::new(None, "#version 0.2.0\n", path).unwrap().version, "0.2.0");
+		assert!(Formatter::new(None, "!\n", path).unwrap().format("!\\\n", path).is_err());
+	}
+}
@@ -1,51 +1,204 @@
-use crate::Flags;
+use std::cmp::Ordering;
 
+use crate::{Diagnostic, Flags, Severity, Utils, Warnings};
+
+#[path = "./checker.rs"]
+mod checker;
+pub use checker::Checker;
+#[path = "./formatter.rs"]
+mod formatter;
+pub use formatter::Formatter;
+#[path = "./memory.rs"]
+mod memory;
+#[path = "./migrator.rs"]
+mod migrator;
+pub use migrator::Migrator;
 #[path = "./preprocessor.rs"]
 mod preprocessor;
+#[path = "./project.rs"]
+mod project;
+use project::Project;
+#[path = "./snapshot.rs"]
+mod snapshot;
+pub use snapshot::Snapshot;
+#[path = "./source_map.rs"]
+mod source_map;
+use source_map::SourceMap;
 #[path = "./versions/handler.rs"]
 mod versions_handler;
 
 pub struct Interpreter {
 	flags: Flags,
 
-	ansi_enabled: bool,
 	version: String,
 	versions_handler: versions_handler::Handler,
 	code: String,
-	code_path: std::path::PathBuf,
+	source_map: SourceMap,
+	snapshot: Option<Snapshot>,
 }
 
 impl Interpreter {
-	pub fn new(version: Option<String>, code: String, code_path: std::path::PathBuf, mut flags: Flags, ansi_enabled: bool) -> Self {
-		let mut preprocessor = preprocessor::Preprocessor::new();
-		preprocessor.run(&code);
-		flags.no_brainfuck |= preprocessor.no_brainfuck;
+	pub fn new(version: Option<String>, code: String, code_path: std::path::PathBuf, mut flags: Flags, snapshot: Option<Snapshot>) -> Result<Self, Diagnostic> {
+		let project = Project::load(&code_path).map_err(|e| Diagnostic::error("project", e))?;
+		if let Some(path) = project.path.as_ref().filter(|_| flags.debug) {
+			Diagnostic::debug(format!("Using the project file {:?}", path)).emit();
+		}
+		// The project version is known before the code is preprocessed, so the # statements depending on the version see it
+		let mut preprocessor = preprocessor::Preprocessor::new(flags.clone(), version.clone(), project.version.clone());
+		let (code, source_map) = preprocessor.run(&code, &code_path).map_err(|e| Diagnostic::error("preprocessor", e))?;
+		let severity = if flags.strict_preprocessor { Severity::Error } else { Severity::Warning };
+		for diagnostic in &preprocessor.diagnostics {
+			diagnostic.to_diagnostic(severity).emit();
+		}
+		if flags.strict_preprocessor && !preprocessor.diagnostics.is_empty() {
+			return Err(Diagnostic::error(
+				"preprocessor",
+				format!("The code was not run because of {} preprocessor error(s) (--strict-preprocessor)", preprocessor.diagnostics.len()),
+			));
+		}
+		// Each setting comes from the flags first, then from the # statements of the code and then from the golden.toml project file
+		flags.no_brainfuck |= preprocessor.no_brainfuck || project.no_brainfuck;
 		flags.no_console |= preprocessor.no_console;
+		flags.warnings = project.warnings.merged(&preprocessor.warnings).merged(&flags.warnings);
 		let final_version = if let Some(ver) = version {
 			ver
 		} else if let Some(ver) = preprocessor.version {
 			ver
+		} else if let Some(ver) = project.version {
+			ver
 		} else {
 			String::from("latest")
 		};
 		if !flags.sebek.iter().any(|val| val.is_some()) {
-			flags.sebek = preprocessor.sebek;
+			flags.sebek = if preprocessor.sebek.iter().any(|val| val.is_some()) { preprocessor.sebek } else { project.sebek };
 		};
+		// Only #non-finite is checked by the preprocessor, the flag and the project file are checked once the version is known
+		let non_finite_sources = [
+			(flags.non_finite.is_some(), String::from("--non-finite")),
+			(project.non_finite.is_some(), format!("`non-finite` in {:?}", project.path.clone().unwrap_or_default())),
+		];
+		flags.non_finite = flags.non_finite.or(preprocessor.non_finite).or(project.non_finite);
+		flags.step_limit = flags.step_limit.or(project.step_limit);
+		flags.input_path = flags.input_path.or(project.input_path);
+		if let Some(path) = &flags.input_path {
+			Utils::set_input_file(path).map_err(|e| Diagnostic::error("io", format!("Couldn't open the input file {:?}: {}", path, e)))?;
+		}
 		let versions_handler = versions_handler::Handler::new();
-		let parsed_version = versions_handler.parse_version(final_version, ansi_enabled);
+		let parsed_version = versions_handler.resolve_version(final_version).map_err(|e| Diagnostic::error("version", e))?;
+		if preprocessor::Preprocessor::compare_versions(&parsed_version, "0.6.0") == Some(Ordering::Less) {
+			for (_, source) in non_finite_sources.iter().filter(|(set, _)| *set) {
+				let message = format!("{} is only used by version 0.6.0 and newer, not {}", source, parsed_version);
+				if flags.strict_preprocessor {
+					return Err(Diagnostic::error("preprocessor", message));
+				}
+				Diagnostic::warning("preprocessor", message).emit();
+			}
+		}
+		for message in Self::unsupported_warnings(&flags.warnings, &parsed_version) {
+			if flags.strict_preprocessor {
+				return Err(Diagnostic::error("preprocessor", message));
+			}
+			Diagnostic::warning("preprocessor", message).emit();
+		}
 
-		Self {
+		Ok(Self {
 			flags,
-			ansi_enabled,
 			code,
 			version: parsed_version,
 			versions_handler,
-			code_path,
-		}
+			source_map,
+			snapshot,
+		})
 	}
 
-	pub fn run(&self) {
+	// The levels set for the warnings the version never gives, they would do nothing
+	fn unsupported_warnings(warnings: &Warnings, version: &str) -> Vec<String> {
+		warnings
+			.unsupported(|warning| preprocessor::Preprocessor::compare_versions(version, warning.since()) != Some(Ordering::Less))
+			.iter()
+			.map(|warning| format!("The `{}` warning is only given by version {} and newer, not {}", warning.name(), warning.since(), version))
+			.collect()
+	}
+
+	pub fn print_versions(ansi_enabled: bool) {
+		versions_handler::Handler::new().print_versions(ansi_enabled);
+	}
+
+	pub fn resolve_version(version: String) -> Result<String, String> {
+		versions_handler::Handler::new().resolve_version(version)
+	}
+
+	pub fn run(&self) -> i32 {
 		self.versions_handler
-			.run(self.version.clone(), self.code.clone(), self.code_path.clone(), self.flags.clone(), self.ansi_enabled);
+			.run(self.version.clone(), self.code.clone(), self.source_map.clone(), self.flags.clone(), self.snapshot.clone())
+	}
+
+	// Runs the code and returns the state it ended in too
+	pub fn run_keeping_state(&self) -> (i32, Option<Snapshot>) {
+		self.versions_handler
+			.run_keeping_state(self.version.clone(), self.code.clone(), self.source_map.clone(), self.flags.clone(), self.snapshot.clone())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Interpreter;
+	use crate::Flags;
+	use std::path::PathBuf;
+
+	// A directory with the golden.toml file choosing the version, removed when dropped
+	struct Project(PathBuf);
+
+	impl Project {
+		fn new(name: &str, version: &str) -> Self {
+			let directory = std::env::temp_dir().join(format!("the-golden-{}-{}", name, std::process::id()));
+			std::fs::create_dir_all(&directory).unwrap();
+			std::fs::write(directory.join("golden.toml"), format!("version = \"{}\"\n", version)).unwrap();
+			Self(directory)
+		}
+
+		fn interpreter(&self, version: Option<&str>, code: &str, flags: Flags) -> Result<Interpreter, String> {
+			Interpreter::new(version.map(String::from), code.to_string(), self.0.join("main.au"), flags, None).map_err(|e| e.to_string())
+		}
+	}
+
+	impl Drop for Project {
+		fn drop(&mut self) {
+			std::fs::remove_dir_all(&self.0).ok();
+		}
+	}
+
+	const BRANCHES: &str = "#if version >= 0.5.0\n!\n#else\n~\n#endif\n";
+
+	#[test]
+	fn conditionals_see_the_project_version() {
+		let project = Project::new("project-conditionals", "0.4.0");
+		let interpreter = project.interpreter(None, BRANCHES, Flags::new()).unwrap();
+		assert_eq!(interpreter.version, "0.4.0");
+		assert!(interpreter.code.contains('~') && !interpreter.code.contains('!'));
+	}
+
+	#[test]
+	fn flags_and_statements_win_over_the_project() {
+		let project = Project::new("project-precedence", "0.5.0");
+		let interpreter = project.interpreter(None, &format!("#version 0.4.0\n{}", BRANCHES), Flags::new()).unwrap();
+		assert_eq!(interpreter.version, "0.4.0");
+		assert!(interpreter.code.contains('~') && !interpreter.code.contains('!'));
+		let interpreter = project.interpreter(Some("0.3.0"), &format!("#version 0.6.0\n{}", BRANCHES), Flags::new()).unwrap();
+		assert_eq!(interpreter.version, "0.3.0");
+		assert!(interpreter.code.contains('~') && !interpreter.code.contains('!'));
+	}
+
+	#[test]
+	fn statements_are_checked_with_the_project_version() {
+		let flags = Flags {
+			strict_preprocessor: true,
+			..Flags::new()
+		};
+		let old = Project::new("project-old-statements", "0.4.0");
+		assert!(old.interpreter(None, "#non-finite error\n!", flags.clone()).is_err_and(|e| e.contains("preprocessor error")));
+		assert!(old.interpreter(None, "!#assert == 1\n", flags.clone()).is_err());
+		let new = Project::new("project-new-statements", "0.6.0");
+		assert!(new.interpreter(None, "#non-finite error\n!#assert == 1\n", flags).is_ok());
 	}
 }
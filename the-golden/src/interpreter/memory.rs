@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+// Only the cells that were written to are stored, so moving the pointer is O(1) no matter how far it goes
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MemoryRow {
+	#[serde(with = "cells")]
+	cells: HashMap<isize, f64>,
+	pointer: isize,
+	start: isize,
+}
+
+impl Default for MemoryRow {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl MemoryRow {
+	pub fn new() -> Self {
+		Self {
+			cells: HashMap::new(),
+			pointer: 0,
+			start: 0,
+		}
+	}
+
+	pub fn get(&self) -> f64 {
+		*self.cells.get(&self.pointer).unwrap_or(&0.0)
+	}
+
+	pub fn set(&mut self, value: f64) {
+		if value == 0.0 {
+			self.cells.remove(&self.pointer);
+		} else {
+			self.cells.insert(self.pointer, value);
+		}
+	}
+
+	// The index of the current cell, counted from the leftmost cell the pointer has ever been at
+	pub fn index(&self) -> usize {
+		self.pointer.abs_diff(self.start)
+	}
+
+	// The cells that aren't zero by their position relative to where the pointer started, sorted
+	pub fn cells(&self) -> Vec<(isize, f64)> {
+		let mut cells = self.cells.iter().filter(|(_, value)| **value != 0.0).map(|(key, value)| (*key, *value)).collect::<Vec<(isize, f64)>>();
+		cells.sort_by_key(|(key, _)| *key);
+		cells
+	}
+
+	// The position of the pointer relative to where it started
+	pub fn pointer(&self) -> isize {
+		self.pointer
+	}
+
+	pub fn move_right(&mut self, steps: i128) -> Result<(), String> {
+		self.pointer = isize::try_from(steps)
+			.ok()
+			.and_then(|steps| self.pointer.checked_add(steps))
+			.ok_or_else(|| format!("Moving the pointer {} cells to the right goes past the end of the memory", steps))?;
+		Ok(())
+	}
+
+	// Returns true if the pointer went to the left of the leftmost cell, which shifts all the indices to the right
+	pub fn move_left(&mut self, steps: i128) -> Result<bool, String> {
+		self.pointer = isize::try_from(steps)
+			.ok()
+			.and_then(|steps| self.pointer.checked_sub(steps))
+			.ok_or_else(|| format!("Moving the pointer {} cells to the left goes past the start of the memory", steps))?;
+		if self.pointer < self.start {
+			self.start = self.pointer;
+			return Ok(true);
+		}
+		Ok(false)
+	}
+}
+
+// The state of the caller that is put aside while a function runs with its own local memory
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CallFrame {
+	pub return_address: usize,
+	pub on_local: bool,
+	pub loops: Vec<usize>,
+	pub local_memory: [MemoryRow; 2],
+	pub active_local_memory: usize,
+}
+
+impl std::fmt::Debug for MemoryRow {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let mut cells = self.cells.iter().filter(|(_, value)| **value != 0.0).map(|(key, value)| (key - self.start, *value)).collect::<Vec<(isize, f64)>>();
+		cells.sort_by_key(|(key, _)| *key);
+		f.debug_map().entries(cells).finish()
+	}
+}
+
+// JSON has no representation for NaN and infinities, so those are stored as strings
+mod cells {
+	use std::collections::HashMap;
+
+	use serde::de::Error;
+	use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+	#[derive(Serialize, Deserialize)]
+	#[serde(untagged)]
+	enum Value {
+		Finite(f64),
+		NonFinite(String),
+	}
+
+	pub fn serialize<S: Serializer>(cells: &HashMap<isize, f64>, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.collect_seq(
+			cells
+				.iter()
+				.map(|(index, value)| (*index, if value.is_finite() { Value::Finite(*value) } else { Value::NonFinite(value.to_string()) })),
+		)
+	}
+
+	pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<isize, f64>, D::Error> {
+		Vec::<(isize, Value)>::deserialize(deserializer)?
+			.into_iter()
+			.map(|(index, value)| match value {
+				Value::Finite(val) => Ok((index, val)),
+				Value::NonFinite(val) => val.parse::<f64>().map(|val| (index, val)).map_err(|_| D::Error::custom(format!("invalid cell value {}", val))),
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::MemoryRow;
+
+	#[test]
+	fn only_written_cells_are_stored() {
+		let mut row = MemoryRow::new();
+		row.move_right(1_000_000_000).unwrap();
+		assert_eq!(row.get(), 0.0);
+		row.set(5.0);
+		row.move_left(999_999_999).unwrap();
+		row.set(-1.0);
+		assert_eq!(row.cells(), vec![(1, -1.0), (1_000_000_000, 5.0)]);
+		// Writing a zero forgets the cell again
+		row.set(0.0);
+		assert_eq!(row.cells(), vec![(1_000_000_000, 5.0)]);
+	}
+
+	#[test]
+	fn reading_doesnt_store_cells() {
+		let mut row = MemoryRow::new();
+		for _ in 0..10 {
+			row.move_right(1).unwrap();
+			assert_eq!(row.get(), 0.0);
+		}
+		assert!(row.cells().is_empty());
+	}
+
+	#[test]
+	fn moving_left_of_the_start_shifts_the_indices() {
+		let mut row = MemoryRow::new();
+		row.move_right(3).unwrap();
+		assert_eq!(row.index(), 3);
+		assert!(!row.move_left(3).unwrap());
+		assert!(row.move_left(2).unwrap());
+		assert_eq!(row.index(), 0);
+		row.move_right(5).unwrap();
+		assert_eq!(row.index(), 5);
+		assert_eq!(row.pointer(), 3);
+	}
+
+	#[test]
+	fn far_moves_are_checked_for_overflow() {
+		let mut row = MemoryRow::new();
+		row.move_right(isize::MAX as i128).unwrap();
+		assert!(row.move_right(1).is_err());
+		assert_eq!(row.pointer(), isize::MAX);
+
+		let mut row = MemoryRow::new();
+		row.move_left(isize::MAX as i128).unwrap();
+		assert!(row.move_left(2).is_err());
+		assert!(row.move_right(i128::MAX).is_err());
+		assert!(row.move_left(i128::MIN).is_err());
+	}
+}
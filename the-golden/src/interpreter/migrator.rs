@@ -0,0 +1,239 @@
+use std::path::Path;
+
+use regex::Regex;
+
+use crate::interpreter::source_map::SourceMap;
+use crate::interpreter::versions_handler::frontend::Lexer;
+use crate::interpreter::versions_handler::Handler;
+
+// The version in which the I/O commands changed and ' became a toggle instead of a prefix
+const TOGGLE_VERSION: [u64; 3] = [0, 4, 0];
+// The first version with the preprocessor
+const PREPROCESSOR_VERSION: [u64; 3] = [0, 3, 0];
+
+// A construct that couldn't be translated exactly
+#[derive(Clone, Debug)]
+pub struct Problem {
+	pub message: String,
+	pub line: usize,
+	pub column: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct Migration {
+	pub code: String,
+	pub problems: Vec<Problem>,
+}
+
+// A loop opened in the old code - whether it is a do-while loop and whether it works on the local memory
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Loop {
+	do_while: bool,
+	local: bool,
+}
+
+pub struct Migrator {
+	handler: Handler,
+	pub from: String,
+	pub to: String,
+}
+
+impl Migrator {
+	// Without `from`, the version is taken from the #version statement of the code
+	pub fn new(from: Option<String>, to: Option<String>, code: &str) -> Result<Self, String> {
+		let handler = Handler::new();
+		let from = match from.or_else(|| Self::find_version(code)) {
+			Some(from) => from,
+			None => return Err(String::from("Couldn't tell which version the code was written for - use the --from flag")),
+		};
+		let to = to.unwrap_or_else(|| String::from("latest"));
+		let (from, to) = (handler.resolve_version(from)?, handler.resolve_version(to)?);
+		if Self::semver(&from) > Self::semver(&to) {
+			return Err(format!("Can't migrate from {} to the older version {}", from, to));
+		}
+		Ok(Self { handler, from, to })
+	}
+
+	pub fn find_version(code: &str) -> Option<String> {
+		let rule = Regex::new(crate::PREPROCESSOR_REGEX).unwrap();
+		let version = rule.find_iter(code).find_map(|statement| {
+			let args = statement.as_str().trim().trim_matches(['#', ':']).split_whitespace().collect::<Vec<&str>>();
+			(args.len() > 1 && args[0].to_lowercase() == "version").then(|| args[1..].join(" "))
+		});
+		version
+	}
+
+	fn semver(version: &str) -> [u64; 3] {
+		let mut semver = [0; 3];
+		for (part, value) in semver.iter_mut().zip(version.split('.')) {
+			*part = value.parse().unwrap_or(0);
+		}
+		semver
+	}
+
+	pub fn migrate(&self, code: &str, code_path: &Path) -> Result<Migration, String> {
+		let grammar = self.handler.grammar(&self.from).unwrap();
+		let (shebang, code) = Lexer::split_shebang(code);
+		let (line, column) = SourceMap::advance(shebang, 1, 1);
+		let mut source_map = SourceMap::new();
+		source_map.push(0, code_path.to_path_buf(), line, column);
+		let mut lexer = Lexer::new(code.to_string(), grammar, source_map);
+		let toggle = Self::semver(&self.from) < TOGGLE_VERSION && Self::semver(&self.to) >= TOGGLE_VERSION;
+
+		let mut output = String::new();
+		let mut problems = vec![];
+		let mut found_version = false;
+		let mut found_no_brainfuck = false;
+		let mut loops: Vec<Loop> = vec![];
+		// True if the output ends with the ' switching back from the local memory, so the next local command can reuse it
+		let mut on_local = false;
+		while let Some((token, line, column, _)) = lexer.next()? {
+			let mut problem = |message: String| problems.push(Problem { message, line, column });
+			if token.starts_with('#') {
+				on_local = false;
+				let args = token.trim().trim_matches(['#', ':']).split_whitespace().collect::<Vec<&str>>();
+				match args.first().map(|name| name.to_lowercase()).unwrap_or_default().as_str() {
+					"version" if args.len() > 1 => {
+						found_version = true;
+						output.push_str(&token.replacen(&args[1..].join(" "), &self.to, 1));
+						continue;
+					}
+					"nobrainfuck" | "no-brainfuck" | "no_brainfuck" => found_no_brainfuck = true,
+					"include" => problem(format!("The included file {} has to be migrated separately", args[1..].join(" "))),
+					_ => {}
+				}
+				output.push_str(&token);
+				continue;
+			}
+			let is_command = !(token.starts_with('"') || token.contains(':') || token.trim().is_empty());
+			if !toggle || !is_command {
+				on_local = false;
+				output.push_str(&token);
+				continue;
+			}
+
+			let (local, command) = match token.strip_prefix('\'') {
+				Some(command) => (true, command),
+				None => (false, token.as_str()),
+			};
+			let translated = match command {
+				"\\." => "$.",
+				"\\," => ".",
+				"$." => "$,",
+				"$," => ",",
+				_ => command,
+			};
+			match command {
+				"[" | "[@" => loops.push(Loop { do_while: command == "[@", local }),
+				"]" | "@]" => {
+					let closed = Loop { do_while: command == "@]", local };
+					if let Some(index) = loops.iter().rposition(|opened| *opened == closed) {
+						// Loops of the same kind are matched to each other no matter which memory they are on now
+						if loops[index + 1..].iter().any(|opened| opened.do_while == closed.do_while) {
+							problem(String::from("Loops on the local and the global memory that overlap instead of being nested can't be translated, since both use the same brackets now"));
+						}
+						loops.remove(index);
+					}
+				}
+				_ if (command.ends_with("?=") || command.ends_with("?>") || command.ends_with("?<")) && loops.last().is_some_and(|current| current.local != local) => {
+					problem(format!(
+						"The break `{}` compares the {} memory inside a loop on the {} memory, so the code after the loop would run on the wrong memory after breaking out of it",
+						token,
+						if local { "local" } else { "global" },
+						if local { "global" } else { "local" }
+					));
+				}
+				_ => {}
+			}
+			if !local {
+				on_local = false;
+				output.push_str(translated);
+			} else if on_local {
+				output.pop();
+				output.push_str(&format!("{}'", translated));
+			} else {
+				output.push_str(&format!("'{}'", translated));
+				on_local = true;
+			}
+		}
+
+		// The #! line has to stay the first one
+		let mut header = shebang.to_string();
+		if !found_version && Self::semver(&self.to) >= PREPROCESSOR_VERSION {
+			header.push_str(&format!("#version {}\n", self.to));
+		}
+		// Division by zero gave infinity (or NaN for 0) before 0.3.0 and is an error since then unless #sebek sets the results
+		if Self::semver(&self.from) < PREPROCESSOR_VERSION && Self::semver(&self.to) >= PREPROCESSOR_VERSION {
+			header.push_str("#sebek -inf|NaN|inf\n");
+		}
+		// The first cell of the secondary memory only starts at 1 since 0.4.0
+		if toggle && !found_no_brainfuck {
+			header.push_str("#no-brainfuck\n");
+		}
+		Ok(Migration {
+			code: header + &output,
+			problems,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Migrator;
+	use std::path::Path;
+
+	fn migrate(from: Option<&str>, to: &str, code: &str) -> String {
+		let migrator = Migrator::new(from.map(String::from), Some(to.to_string()), code).unwrap();
+		migrator.migrate(code, Path::new("maumivu.au")).unwrap().code
+	}
+
+	#[test]
+	fn io_commands_are_rewritten() {
+		assert_eq!(migrate(Some("0.3.0"), "0.4.0", "\\.\\,$.$,:"), "#version 0.4.0\n#no-brainfuck\n$..$,,:");
+	}
+
+	#[test]
+	fn local_commands_share_one_toggle() {
+		assert_eq!(migrate(Some("0.3.0"), "0.4.0", "'\\.'\\,:"), "#version 0.4.0\n#no-brainfuck\n'$..':");
+	}
+
+	#[test]
+	fn existing_statements_are_reused() {
+		assert_eq!(migrate(None, "0.5.0", "#version 0.3.0\n\\.:"), "#no-brainfuck\n#version 0.5.0\n$.:");
+		assert_eq!(migrate(Some("0.3.0"), "0.4.0", "#no-brainfuck\n\\.:"), "#version 0.4.0\n#no-brainfuck\n$.:");
+	}
+
+	#[test]
+	fn statements_can_be_spaced_with_any_whitespace() {
+		assert_eq!(migrate(None, "0.4.0", "#version\t 0.3.0\n\\.:"), "#no-brainfuck\n#version\t 0.4.0\n$.:");
+	}
+
+	#[test]
+	fn headers_depend_on_the_target_version() {
+		// 0.2.0 has no preprocessor and the same I/O commands
+		assert_eq!(migrate(Some("0.1.0"), "0.2.0", "\\.:"), "\\.:");
+		assert_eq!(migrate(Some("0.1.0"), "0.3.0", "\\.:"), "#version 0.3.0\n#sebek -inf|NaN|inf\n\\.:");
+	}
+
+	#[test]
+	fn division_by_zero_keeps_its_results() {
+		assert_eq!(migrate(Some("0.2.0"), "0.3.0", "!/\\.:"), "#version 0.3.0\n#sebek -inf|NaN|inf\n!/\\.:");
+		assert_eq!(migrate(Some("0.2.0"), "0.4.0", "!/\\.:"), "#version 0.4.0\n#sebek -inf|NaN|inf\n#no-brainfuck\n!/$.:");
+		// The results set in the code since 0.3.0 stay as they are
+		assert_eq!(migrate(Some("0.3.0"), "0.5.0", "#sebek 1|2|3\n!/$.:"), "#version 0.5.0\n#no-brainfuck\n#sebek 1|2|3\n!/$,:");
+	}
+
+	#[test]
+	fn shebang_stays_first() {
+		assert_eq!(
+			migrate(Some("0.3.0"), "0.4.0", "#!/usr/bin/env the-golden\n\\.:"),
+			"#!/usr/bin/env the-golden\n#version 0.4.0\n#no-brainfuck\n$.:"
+		);
+	}
+
+	#[test]
+	fn invalid_versions_are_rejected() {
+		assert!(Migrator::new(None, None, "\\.:").is_err());
+		assert!(Migrator::new(Some(String::from("0.5.0")), Some(String::from("0.4.0")), "").is_err());
+	}
+}
@@ -1,38 +1,171 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
 use regex::Regex;
 
-use crate::Utils;
+use crate::interpreter::source_map::{MacroExpansion, SourceMap};
+use crate::interpreter::versions_handler::frontend::Lexer;
+use crate::interpreter::versions_handler::Handler;
+use crate::{Flags, NonFinitePolicy, Severity, Utils, Warning, WarningLevel, Warnings};
 
+// A problem with a statement that doesn't stop the preprocessing
 #[derive(Clone, Debug)]
-pub struct Warnings {
-	pub too_left_pointer: bool,
+pub struct Diagnostic {
+	pub message: String,
+	pub suggestion: Option<String>,
+	pub file_path: PathBuf,
+	pub line: usize,
+	pub column: usize,
+}
+
+impl Diagnostic {
+	pub fn to_diagnostic(&self, severity: Severity) -> crate::Diagnostic {
+		crate::Diagnostic::new(severity, Some("preprocessor"), self.to_string()).at(&self.file_path, self.line, self.column)
+	}
+}
+
+impl std::fmt::Display for Diagnostic {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{} at {}:{} in {:?}", self.message, self.line, self.column, self.file_path.file_name().unwrap_or_default())?;
+		if let Some(suggestion) = &self.suggestion {
+			write!(f, ", did you mean `{}`?", suggestion)?;
+		}
+		Ok(())
+	}
+}
+
+const STATEMENTS: [&str; 23] = [
+	"version",
+	"no-brainfuck",
+	"nobrainfuck",
+	"no_brainfuck",
+	"no-console",
+	"noconsole",
+	"no_console",
+	"disable-warnings",
+	"disablewarnings",
+	"disable_warnings",
+	"deny",
+	"sebek",
+	"non-finite",
+	"nonfinite",
+	"non_finite",
+	"include",
+	"define",
+	"if",
+	"elif",
+	"else",
+	"endif",
+	"expect",
+	"assert",
+];
+
+// The oldest version using a statement, and what to tell the code using it with an older one
+type VersionRequirement = (&'static str, String);
+
+#[derive(Clone, Debug)]
+struct Macro {
+	body: String,
+	file_path: PathBuf,
+	line: usize,
+	column: usize,
+}
+
+// One #if ... #endif block
+#[derive(Clone, Debug)]
+struct Conditional {
+	parent_active: bool,
+	active: bool,
+	taken: bool,
+	seen_else: bool,
+	line: usize,
+	column: usize,
 }
 
 #[derive(Clone, Debug)]
 pub struct Preprocessor {
-	pub disabled_warnings: Warnings,
+	flags: Flags,
+	selected_version: Option<String>,
+	// From the project file, the # statements win over it
+	project_version: Option<String>,
+
+	pub warnings: Warnings,
 
 	pub no_brainfuck: bool,
 	pub no_console: bool,
+	pub non_finite: Option<NonFinitePolicy>,
 	pub sebek: [Option<f64>; 3],
 	pub version: Option<String>,
+
+	pub diagnostics: Vec<Diagnostic>,
+
+	// How deep in the includes each setting was set, so the including file always overrides the included ones
+	settings_depths: HashMap<&'static str, usize>,
+	macros: HashMap<String, Macro>,
+	// The statements only some versions use with the oldest one, checked once the version can't change anymore
+	version_checks: Vec<(&'static str, Diagnostic)>,
 }
 
 impl Preprocessor {
-	pub fn new() -> Self {
+	pub fn new(flags: Flags, selected_version: Option<String>, project_version: Option<String>) -> Self {
 		Self {
-			disabled_warnings: Warnings { too_left_pointer: false },
+			flags,
+			selected_version,
+			project_version,
+
+			warnings: Warnings::new(),
 
 			no_brainfuck: false,
 			no_console: false,
+			non_finite: None,
 			sebek: [None, None, None],
 			version: None,
+
+			diagnostics: vec![],
+
+			settings_depths: HashMap::new(),
+			macros: HashMap::new(),
+			version_checks: vec![],
+		}
+	}
+
+	pub fn run(&mut self, code: &str, code_path: &Path) -> Result<(String, SourceMap), String> {
+		let mut output = String::new();
+		let mut source_map = SourceMap::new();
+		let mut include_stack = vec![code_path.canonicalize().unwrap_or_else(|_| code_path.to_path_buf())];
+		self.expand(code, code_path, &mut include_stack, &mut output, &mut source_map)?;
+		// A #version statement can come after the statements needing a newer one
+		let version = self.current_version();
+		for (oldest, mut diagnostic) in std::mem::take(&mut self.version_checks) {
+			if Self::compare_versions(&version, oldest) == Some(Ordering::Less) {
+				diagnostic.message = format!("{}, not {}", diagnostic.message, version);
+				self.diagnostics.push(diagnostic);
+			}
 		}
+		Ok((output, source_map))
 	}
 
-	pub fn run(&mut self, code: &str) {
+	fn expand(&mut self, code: &str, code_path: &Path, include_stack: &mut Vec<PathBuf>, output: &mut String, source_map: &mut SourceMap) -> Result<(), String> {
+		let depth = include_stack.len() - 1;
 		let rule = Regex::new(crate::PREPROCESSOR_REGEX).unwrap();
-		let statements = rule.find_iter(code).map(|m| m.as_str().trim()).collect::<Vec<&str>>();
-		for &statement in &statements {
+		let (shebang, _) = Lexer::split_shebang(code);
+		let (mut line, mut column) = SourceMap::advance(shebang, 1, 1);
+		let mut copied_until = shebang.len();
+		let mut in_comment = false;
+		let mut scanned_until = copied_until;
+		let mut conditionals: Vec<Conditional> = vec![];
+		source_map.push(output.len(), code_path.to_path_buf(), line, column);
+		for statement_match in rule.find_iter(code) {
+			if statement_match.start() < scanned_until {
+				continue;
+			}
+			in_comment ^= code[scanned_until..statement_match.start()].matches('"').count() % 2 == 1;
+			scanned_until = statement_match.start();
+			if in_comment {
+				continue;
+			}
+			let statement = statement_match.as_str().trim();
 			let mut statement_chars = statement.chars();
 			statement_chars.next();
 			if statement.ends_with(':') {
@@ -41,45 +174,543 @@ impl Preprocessor {
 			if statement.ends_with('#') {
 				statement_chars.next_back();
 			}
-			let args = statement_chars.as_str().split(' ').collect::<Vec<&str>>();
+			let args = statement_chars.as_str().split_whitespace().collect::<Vec<&str>>();
 			if args.is_empty() {
 				continue;
 			}
-			let args_count = args.len();
-			match args[0].to_lowercase().as_str() {
-				"version" => {
-					if args_count < 2 {
-						continue;
-					}
-					self.version = Some(args[1].to_string());
+			let (statement_line, statement_column) = SourceMap::advance(&code[copied_until..statement_match.start()], line, column);
+			let statement_name = args[0].to_lowercase();
+			if matches!(statement_name.as_str(), "if" | "elif" | "else" | "endif") {
+				let active = conditionals.last().is_none_or(|conditional| conditional.active);
+				if active {
+					output.push_str(&code[copied_until..statement_match.start()]);
 				}
-				"nobrainfuck" | "no-brainfuck" | "no_brainfuck" => self.no_brainfuck = true,
-				"noconsole" | "no-console" | "no_console" => {
-					if args_count < 2 {
-						self.no_console = true;
-						continue;
+				(line, column) = SourceMap::advance(&code[copied_until..statement_match.end()], line, column);
+				copied_until = statement_match.end();
+				let location = format!("{}:{} in {:?}", statement_line, statement_column, code_path.file_name().unwrap_or_default());
+				let condition = &args[1..];
+				match statement_name.as_str() {
+					"if" => {
+						let value = active && self.evaluate_condition(condition).map_err(|e| format!("{} in the #if at {}", e, location))?;
+						conditionals.push(Conditional {
+							parent_active: active,
+							active: value,
+							taken: value,
+							seen_else: false,
+							line: statement_line,
+							column: statement_column,
+						});
 					}
-					self.no_console = args[1].to_lowercase() != "false";
-				}
-				"disablewarnings" | "disable-warnings" | "disable_warnings" => {
-					if args_count < 2 {
-						continue;
+					"elif" | "else" => {
+						let is_else = statement_name == "else";
+						let conditional = match conditionals.last() {
+							Some(conditional) if !conditional.seen_else => conditional.clone(),
+							Some(_) => return Err(format!("#{} after #else at {}", statement_name, location)),
+							None => return Err(format!("#{} without #if at {}", statement_name, location)),
+						};
+						let value = conditional.parent_active
+							&& !conditional.taken
+							&& (is_else || self.evaluate_condition(condition).map_err(|e| format!("{} in the #elif at {}", e, location))?);
+						let last = conditionals.last_mut().unwrap();
+						last.active = value;
+						last.taken |= value;
+						last.seen_else = is_else;
 					}
-					match args[1].to_lowercase().as_str() {
-						"too-left-pointer" | "tooleftpointer" => {
-							self.disabled_warnings.too_left_pointer = true;
+					_ => {
+						if conditionals.pop().is_none() {
+							return Err(format!("#endif without #if at {}", location));
 						}
-						_ => {}
 					}
 				}
-				"sebek" => {
-					if args_count < 2 {
-						continue;
+				source_map.push(output.len(), code_path.to_path_buf(), line, column);
+				continue;
+			}
+			if !conditionals.last().is_none_or(|conditional| conditional.active) {
+				continue;
+			}
+			match statement_name.as_str() {
+				"include" if args.len() >= 2 => {}
+				"define" if args.len() >= 3 => {
+					// The body is kept as written, from its first command to the end of the statement
+					let body_offset = args[2].as_ptr() as usize - statement.as_ptr() as usize;
+					let body = statement_chars.as_str()[body_offset - 1..].trim_end();
+					self.macros.insert(
+						args[1].to_string(),
+						Macro {
+							body: body.to_string(),
+							file_path: code_path.to_path_buf(),
+							line: statement_line,
+							column: statement_column + body_offset,
+						},
+					);
+					continue;
+				}
+				_ => {
+					if let Some(called_macro) = self.macros.get(args[0]).cloned() {
+						output.push_str(&code[copied_until..statement_match.start()]);
+						(line, column) = SourceMap::advance(&code[copied_until..statement_match.end()], line, column);
+						copied_until = statement_match.end();
+
+						source_map.push_expansion(
+							output.len(),
+							called_macro.file_path,
+							called_macro.line,
+							called_macro.column,
+							MacroExpansion {
+								name: args[0].to_string(),
+								file_path: code_path.to_path_buf(),
+								line: statement_line,
+								column: statement_column,
+							},
+						);
+						output.push_str(&called_macro.body);
+						source_map.push(output.len(), code_path.to_path_buf(), line, column);
+					} else {
+						let diagnostic = |message, suggestion| Diagnostic {
+							message,
+							suggestion,
+							file_path: code_path.to_path_buf(),
+							line: statement_line,
+							column: statement_column,
+						};
+						match self.apply_statement(&args, depth) {
+							Ok(Some((oldest, message))) => self.version_checks.push((oldest, diagnostic(message, None))),
+							Ok(None) => {}
+							Err((message, suggestion)) => self.diagnostics.push(diagnostic(message, suggestion)),
+						}
+					}
+					continue;
+				}
+			}
+			let included = args[1..].join(" ");
+			let included = included.trim_matches('"');
+			let mut included_path = code_path.parent().map(|parent| parent.to_path_buf()).unwrap_or_default();
+			included_path.push(included);
+			let included_code = std::fs::read_to_string(&included_path).map_err(|e| {
+				format!(
+					"Couldn't include {:?} at {}:{} in {:?}: {}",
+					included_path,
+					statement_line,
+					statement_column,
+					code_path.file_name().unwrap_or_default(),
+					e
+				)
+			})?;
+			let canonical_path = included_path.canonicalize().unwrap_or_else(|_| included_path.clone());
+			if include_stack.contains(&canonical_path) {
+				let mut cycle = include_stack.iter().skip_while(|path| **path != canonical_path).map(|path| format!("{:?}", path)).collect::<Vec<String>>();
+				cycle.push(format!("{:?}", canonical_path));
+				return Err(format!("Include cycle detected: {}", cycle.join(" -> ")));
+			}
+
+			output.push_str(&code[copied_until..statement_match.start()]);
+			(line, column) = SourceMap::advance(&code[copied_until..statement_match.end()], line, column);
+			copied_until = statement_match.end();
+
+			include_stack.push(canonical_path);
+			self.expand(&included_code, &included_path, include_stack, output, source_map)?;
+			include_stack.pop();
+			source_map.push(output.len(), code_path.to_path_buf(), line, column);
+		}
+		if let Some(conditional) = conditionals.last() {
+			return Err(format!(
+				"#if at {}:{} in {:?} is never closed with #endif",
+				conditional.line,
+				conditional.column,
+				code_path.file_name().unwrap_or_default()
+			));
+		}
+		output.push_str(&code[copied_until..]);
+		Ok(())
+	}
+
+	fn evaluate_condition(&self, tokens: &[&str]) -> Result<bool, String> {
+		let mut position = 0;
+		let value = self.evaluate_or(tokens, &mut position)?;
+		if let Some(token) = tokens.get(position) {
+			return Err(format!("Unexpected `{}`", token));
+		}
+		Ok(value)
+	}
+
+	fn evaluate_or(&self, tokens: &[&str], position: &mut usize) -> Result<bool, String> {
+		let mut value = self.evaluate_and(tokens, position)?;
+		while tokens.get(*position) == Some(&"||") {
+			*position += 1;
+			value |= self.evaluate_and(tokens, position)?;
+		}
+		Ok(value)
+	}
+
+	fn evaluate_and(&self, tokens: &[&str], position: &mut usize) -> Result<bool, String> {
+		let mut value = self.evaluate_term(tokens, position)?;
+		while tokens.get(*position) == Some(&"&&") {
+			*position += 1;
+			value &= self.evaluate_term(tokens, position)?;
+		}
+		Ok(value)
+	}
+
+	fn evaluate_term(&self, tokens: &[&str], position: &mut usize) -> Result<bool, String> {
+		let token = match tokens.get(*position) {
+			Some(token) => *token,
+			None => return Err(String::from("Missing condition")),
+		};
+		*position += 1;
+		if token == "!" {
+			return Ok(!self.evaluate_term(tokens, position)?);
+		}
+		let (negated, name) = match token.strip_prefix('!') {
+			Some(name) => (true, name),
+			None => (false, token),
+		};
+		let comparison = match tokens.get(*position) {
+			Some(&operator) if ["==", "!=", "<", "<=", ">", ">="].contains(&operator) => match tokens.get(*position + 1) {
+				Some(&value) => {
+					*position += 2;
+					Some((operator, value))
+				}
+				None => return Err(format!("Missing value after `{}`", operator)),
+			},
+			_ => None,
+		};
+		let value = match (name.to_lowercase().as_str(), comparison) {
+			("version", Some((operator, value))) => Self::compare(Self::compare_versions(&self.current_version(), value), operator),
+			("version", None) => return Err(String::from("The version has to be compared to something, for example `version >= 0.4.0`")),
+			("nobrainfuck" | "no-brainfuck" | "no_brainfuck", None) => self.flags.no_brainfuck || self.no_brainfuck,
+			("noconsole" | "no-console" | "no_console", None) => self.flags.no_console || self.no_console,
+			("debug", None) => self.flags.debug,
+			("debug-heavy" | "debug_heavy", None) => self.flags.debug_heavy,
+			("nobrainfuck" | "no-brainfuck" | "no_brainfuck" | "noconsole" | "no-console" | "no_console" | "debug" | "debug-heavy" | "debug_heavy", Some(_)) => {
+				return Err(format!("`{}` can't be compared to a value", name))
+			}
+			_ => {
+				let symbol = self.flags.defines.get(name).cloned().or_else(|| self.macros.get(name).map(|defined_macro| defined_macro.body.clone()));
+				match (symbol, comparison) {
+					(Some(symbol), Some((operator, value))) => match (symbol.parse::<f64>(), value.parse::<f64>()) {
+						(Ok(symbol), Ok(value)) => Self::compare(symbol.partial_cmp(&value), operator),
+						_ => Self::compare(Some(symbol.as_str().cmp(value)), operator),
+					},
+					(Some(_), None) => true,
+					(None, _) => false,
+				}
+			}
+		};
+		Ok(value != negated)
+	}
+
+	fn compare(ordering: Option<Ordering>, operator: &str) -> bool {
+		match ordering {
+			Some(ordering) => match operator {
+				"==" => ordering == Ordering::Equal,
+				"!=" => ordering != Ordering::Equal,
+				"<" => ordering == Ordering::Less,
+				"<=" => ordering != Ordering::Greater,
+				">" => ordering == Ordering::Greater,
+				_ => ordering != Ordering::Less,
+			},
+			None => operator == "!=",
+		}
+	}
+
+	pub fn compare_versions(left: &str, right: &str) -> Option<Ordering> {
+		let parse = |version: &str| version.split('-').next().unwrap_or_default().split('.').map(|part| part.parse::<u64>().ok()).collect::<Option<Vec<u64>>>();
+		let (mut left, mut right) = (parse(left)?, parse(right)?);
+		let length = left.len().max(right.len());
+		left.resize(length, 0);
+		right.resize(length, 0);
+		Some(left.cmp(&right))
+	}
+
+	// The version the code will run with, as far as the preprocessor knows at this point
+	fn current_version(&self) -> String {
+		let version = self
+			.selected_version
+			.clone()
+			.or_else(|| self.version.clone())
+			.or_else(|| self.project_version.clone())
+			.unwrap_or_else(|| String::from("latest"));
+		Handler::new().resolve_version(version.clone()).unwrap_or(version)
+	}
+
+	// Settings from the file closer to the main one win, and if they are in the same file, the last one wins
+	fn claim(&mut self, setting: &'static str, depth: usize) -> bool {
+		if let Some(set_at) = self.settings_depths.get(setting) {
+			if *set_at < depth {
+				return false;
+			}
+		}
+		self.settings_depths.insert(setting, depth);
+		true
+	}
+
+	// Returns the problem with the statement and possibly what was meant instead
+	// The requirement is returned for the statements not every version uses
+	fn apply_statement(&mut self, args: &[&str], depth: usize) -> Result<Option<VersionRequirement>, (String, Option<String>)> {
+		let args_count = args.len();
+		match args[0].to_lowercase().as_str() {
+			"version" => {
+				if args_count < 2 {
+					return Err((String::from("#version needs the version to use, for example `#version 0.4.0`"), None));
+				}
+				if self.claim("version", depth) {
+					self.version = Some(args[1..].join(" "));
+				}
+			}
+			"nobrainfuck" | "no-brainfuck" | "no_brainfuck" => {
+				if self.claim("no_brainfuck", depth) {
+					self.no_brainfuck = true;
+				}
+			}
+			"noconsole" | "no-console" | "no_console" => {
+				if !self.claim("no_console", depth) {
+					return Ok(None);
+				}
+				if args_count < 2 {
+					self.no_console = true;
+					return Ok(None);
+				}
+				self.no_console = args[1].to_lowercase() != "false";
+			}
+			statement @ ("disablewarnings" | "disable-warnings" | "disable_warnings" | "deny") => {
+				let (level, action) = if statement == "deny" { (WarningLevel::Deny, "deny") } else { (WarningLevel::Allow, "disable") };
+				if args_count < 2 {
+					return Err((format!("#{} needs the warning to {}, for example `#{} too-left-pointer`", args[0], action, args[0]), None));
+				}
+				let warnings = Warning::parse(args[1]).map_err(|suggestion| (format!("Unknown warning `{}`", args[1]), suggestion.map(String::from)))?;
+				for warning in warnings {
+					if self.claim(warning.name(), depth) {
+						self.warnings.set(warning, level);
 					}
-					self.sebek = Utils::parse_sebek(args[1]);
 				}
-				_ => {}
 			}
+			"sebek" => {
+				if args_count < 2 {
+					return Err((String::from("#sebek needs the results of division by zero, for example `#sebek -1|0|1`"), None));
+				}
+				let sebek = Utils::parse_sebek(args[1]).map_err(|e| (e, None))?;
+				if self.claim("sebek", depth) {
+					self.sebek = sebek;
+				}
+			}
+			"nonfinite" | "non-finite" | "non_finite" => {
+				if args_count < 2 {
+					return Err((format!("#{} needs the policy for NaN and infinite results: `error`, `saturate` or a number", args[0]), None));
+				}
+				let policy = Utils::parse_non_finite(args[1]).map_err(|e| (e, None))?;
+				if self.claim("non_finite", depth) {
+					self.non_finite = Some(policy);
+				}
+				return Ok(Some(("0.6.0", format!("#{} is only used by version 0.6.0 and newer", args[0]))));
+			}
+			// Only checked by the test command
+			"expect" => {}
+			// Checked when the code runs, but the version is checked even without the --assertions flag
+			"assert" => return Ok(Some(("0.5.0", String::from("#assert is only checked by version 0.5.0 and newer")))),
+			"include" => return Err((String::from("#include needs the path of the file to include"), None)),
+			"define" => return Err((String::from("#define needs the name of the macro and the code it stands for"), None)),
+			_ => {
+				let candidates = STATEMENTS.iter().copied().chain(self.macros.keys().map(|name| name.as_str())).collect::<Vec<&str>>();
+				let suggestion = Utils::closest_match(args[0], &candidates).map(|name| format!("#{}", name));
+				return Err((format!("Unknown statement `#{}`", args[0]), suggestion));
+			}
+		}
+		Ok(None)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Preprocessor;
+	use crate::interpreter::source_map::SourceMap;
+	use crate::Flags;
+	use std::path::Path;
+
+	fn preprocess(code: &str, path: &Path, defines: &[&str]) -> Result<String, String> {
+		let mut flags = Flags::new();
+		for name in defines {
+			flags.defines.insert(name.to_string(), String::new());
 		}
+		Preprocessor::new(flags, None, None).run(code, path).map(|(code, _)| code)
+	}
+
+	const NESTED: &str = "#if A\na\n#if B\nab\n#else\na!b\n#endif\n#elif C\nc\n#else\nnone\n#endif\n";
+
+	#[test]
+	fn nested_conditionals_keep_one_branch() {
+		let path = Path::new("maumivu.au");
+		assert_eq!(preprocess(NESTED, path, &["A", "B"]).unwrap(), "a\nab\n");
+		assert_eq!(preprocess(NESTED, path, &["A"]).unwrap(), "a\na!b\n");
+		assert_eq!(preprocess(NESTED, path, &["A", "C"]).unwrap(), "a\na!b\n");
+		assert_eq!(preprocess(NESTED, path, &["B", "C"]).unwrap(), "c\n");
+		assert_eq!(preprocess(NESTED, path, &[]).unwrap(), "none\n");
+	}
+
+	#[test]
+	fn branches_inside_an_inactive_one_are_skipped() {
+		let code = "#if A\n#if B\nab\n#else\na!b\n#endif\n#endif\nend\n";
+		assert_eq!(preprocess(code, Path::new("maumivu.au"), &["B"]).unwrap(), "end\n");
+	}
+
+	#[test]
+	fn unbalanced_conditionals_are_errors() {
+		let path = Path::new("maumivu.au");
+		assert!(preprocess("#if A\n#else\n#else\n#endif\n", path, &[]).unwrap_err().contains("#else after #else"));
+		assert!(preprocess("#else\n", path, &[]).unwrap_err().contains("#else without #if"));
+		assert!(preprocess("#elif A\n", path, &[]).unwrap_err().contains("#elif without #if"));
+		assert!(preprocess("#endif\n", path, &[]).unwrap_err().contains("#endif without #if"));
+		assert!(preprocess("#if A\n#if B\n#endif\n", path, &[]).unwrap_err().contains("never closed"));
+	}
+
+	fn diagnostics(code: &str) -> Vec<(String, Option<String>)> {
+		let mut preprocessor = Preprocessor::new(Flags::new(), None, None);
+		preprocessor.run(code, Path::new("maumivu.au")).unwrap();
+		preprocessor.diagnostics.into_iter().map(|diagnostic| (diagnostic.message, diagnostic.suggestion)).collect()
+	}
+
+	#[test]
+	fn unknown_statements_get_suggestions() {
+		assert_eq!(diagnostics("#nobrianfuck\n"), [(String::from("Unknown statement `#nobrianfuck`"), Some(String::from("#nobrainfuck")))]);
+		assert_eq!(diagnostics("#define NL >|10|!.<\n#NK#\n")[0].1.as_deref(), Some("#NL"));
+		assert_eq!(diagnostics("#disable-warnings too-left-pionter\n")[0].1.as_deref(), Some("too-left-pointer"));
+		assert_eq!(diagnostics("#hello-there\n")[0].1, None);
+	}
+
+	#[test]
+	fn malformed_statements_are_reported() {
+		let path = Path::new("maumivu.au");
+		let diagnostic = {
+			let mut preprocessor = Preprocessor::new(Flags::new(), None, None);
+			preprocessor.run("!\n  #version\n", path).unwrap();
+			preprocessor.diagnostics.remove(0)
+		};
+		assert!(diagnostic.message.starts_with("#version needs the version"));
+		assert_eq!((diagnostic.line, diagnostic.column), (2, 3));
+		assert_eq!(diagnostics("#sebek a|b|c\n").len(), 1);
+		assert_eq!(diagnostics("#include\n").len(), 1);
+		assert_eq!(diagnostics("#define NL\n").len(), 1);
+	}
+
+	#[test]
+	fn statement_arguments_are_split_on_any_whitespace() {
+		assert!(diagnostics("#version  0.3.0\n#sebek\t1|2|3\n#disable-warnings \t too-left-pointer#\n#\n").is_empty());
+		let mut preprocessor = Preprocessor::new(Flags::new(), None, None);
+		preprocessor.run("#version  0.3.0\n#sebek\t1|2|3\n", Path::new("maumivu.au")).unwrap();
+		assert_eq!(preprocessor.version.as_deref(), Some("0.3.0"));
+		assert_eq!(preprocessor.sebek, [Some(1.0), Some(2.0), Some(3.0)]);
+		assert_eq!(preprocess("#define  NL\t>|10|!.<  \n#NL#", Path::new("maumivu.au"), &[]).unwrap(), "#define  NL\t>|10|!.<  \n>|10|!.<");
+	}
+
+	#[test]
+	fn include_cycles_are_detected() {
+		let directory = std::env::temp_dir().join(format!("the-golden-include-cycle-{}", std::process::id()));
+		std::fs::create_dir_all(&directory).unwrap();
+		std::fs::write(directory.join("a.au"), "#include b.au\n").unwrap();
+		std::fs::write(directory.join("b.au"), "#include a.au\n").unwrap();
+		std::fs::write(directory.join("self.au"), "#include self.au\n").unwrap();
+		std::fs::write(directory.join("shared.au"), "!").unwrap();
+		std::fs::write(directory.join("twice.au"), "#include shared.au\n#include shared.au\n").unwrap();
+
+		let preprocess_file = |name: &str| {
+			let path = directory.join(name);
+			preprocess(&std::fs::read_to_string(&path).unwrap(), &path, &[])
+		};
+		assert!(preprocess_file("a.au").unwrap_err().contains("Include cycle detected"));
+		assert!(preprocess_file("self.au").unwrap_err().contains("Include cycle detected"));
+		// Including the same file twice isn't a cycle
+		assert_eq!(preprocess_file("twice.au").unwrap(), "!!");
+		std::fs::remove_dir_all(&directory).ok();
+	}
+
+	#[test]
+	fn macros_expand_to_their_body() {
+		let path = Path::new("maumivu.au");
+		let code = "#define NL >|10|!.<\n|72|!.#NL#|105|!.#NL\n";
+		let mut preprocessor = Preprocessor::new(Flags::new(), None, None);
+		let (output, source_map) = preprocessor.run(code, path).unwrap();
+		assert_eq!(output, "#define NL >|10|!.<\n|72|!.>|10|!.<|105|!.>|10|!.<");
+
+		// The body maps to the definition, with the call it was expanded from, and the code after it to the call site
+		let expansions = source_map
+			.segments
+			.iter()
+			.filter_map(|segment| segment.expansion.as_ref())
+			.map(|expansion| (expansion.line, expansion.column))
+			.collect::<Vec<(usize, usize)>>();
+		assert_eq!(expansions, [(2, 7), (2, 18)]);
+		let body = source_map.segments.iter().find(|segment| segment.expansion.is_some()).unwrap();
+		assert_eq!((body.line, body.column), (1, 12));
+		assert!(source_map.describe_expansion(1).contains("in the expansion of the macro NL invoked at 2:7"));
+		assert_eq!(source_map.describe_expansion(2), "");
+
+		// Macros can't be used before they are defined, and the name is case-sensitive
+		let mut preprocessor = Preprocessor::new(Flags::new(), None, None);
+		preprocessor.run("#NL#\n#define NL >|10|!.<\n#nl#\n", path).unwrap();
+		assert_eq!(preprocessor.diagnostics.len(), 2);
+	}
+
+	#[test]
+	fn assertions_need_version_0_5_0() {
+		let path = Path::new("maumivu.au");
+		for (version, problems) in [("0.4.0", 1), ("0.5.0", 0)] {
+			let mut preprocessor = Preprocessor::new(Flags::new(), Some(String::from(version)), None);
+			preprocessor.run("!#assert == 1\n", path).unwrap();
+			assert_eq!(preprocessor.diagnostics.len(), problems, "#assert on {}", version);
+		}
+	}
+
+	#[test]
+	fn versions_are_checked_after_all_statements() {
+		let path = Path::new("maumivu.au");
+		for (code, problems) in [
+			("#non-finite error\n#version 0.4.0\n", 1),
+			("#version 0.4.0\n#non-finite error\n", 1),
+			("#non-finite error\n#version 0.6.0\n", 0),
+			("!#assert == 1\n#version 0.4.0\n", 1),
+			("!#assert == 1\n#version 0.5.0\n", 0),
+		] {
+			let mut preprocessor = Preprocessor::new(Flags::new(), None, None);
+			preprocessor.run(code, path).unwrap();
+			assert_eq!(preprocessor.diagnostics.len(), problems, "{:?}", code);
+			if let Some(diagnostic) = preprocessor.diagnostics.first() {
+				// Reported at the statement, not at the #version one
+				assert!(diagnostic.message.ends_with("not 0.4.0"), "{}", diagnostic.message);
+				assert_eq!(diagnostic.line, code.lines().position(|line| !line.starts_with("#version")).unwrap() + 1);
+			}
+		}
+		// The selected version wins over the statement
+		let mut preprocessor = Preprocessor::new(Flags::new(), Some(String::from("0.6.0")), None);
+		preprocessor.run("#non-finite saturate\n#version 0.4.0\n", path).unwrap();
+		assert!(preprocessor.diagnostics.is_empty());
+	}
+
+	#[test]
+	fn including_file_settings_win() {
+		let directory = std::env::temp_dir().join(format!("the-golden-include-depth-{}", std::process::id()));
+		std::fs::create_dir_all(directory.join("lib")).unwrap();
+		std::fs::write(directory.join("main.au"), "#version 0.3.0\n#include lib/outer.au\n!").unwrap();
+		std::fs::write(directory.join("lib").join("outer.au"), "#version 0.2.0\n#sebek 1|2|3\n#include inner.au\n>").unwrap();
+		std::fs::write(directory.join("lib").join("inner.au"), "#sebek 4|5|6\n#no-brainfuck\n<").unwrap();
+
+		let path = directory.join("main.au");
+		let mut preprocessor = Preprocessor::new(Flags::new(), None, None);
+		let (code, source_map) = preprocessor.run(&std::fs::read_to_string(&path).unwrap(), &path).unwrap();
+		assert!(code.ends_with("<>!"));
+		// The main file sets the version even though the included one does it later, and outer.au overrides inner.au
+		assert_eq!(preprocessor.version.as_deref(), Some("0.3.0"));
+		assert_eq!(preprocessor.sebek, [Some(1.0), Some(2.0), Some(3.0)]);
+		assert!(preprocessor.no_brainfuck);
+
+		// Every command maps back to the file and line it was written on, paths are relative to the including file
+		let origin = |command: char| {
+			let position = code.find(command).unwrap();
+			let segment = source_map.segments.iter().rev().find(|segment| segment.start <= position).unwrap();
+			let (line, _) = SourceMap::advance(&code[segment.start..position], segment.line, segment.column);
+			(segment.file_path.file_name().unwrap().to_str().unwrap().to_string(), line)
+		};
+		assert_eq!(origin('<'), (String::from("inner.au"), 3));
+		assert_eq!(origin('>'), (String::from("outer.au"), 4));
+		assert_eq!(origin('!'), (String::from("main.au"), 3));
+		std::fs::remove_dir_all(&directory).ok();
 	}
 }
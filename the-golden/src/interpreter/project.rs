@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::{NonFinitePolicy, Utils, Warning, WarningLevel, Warnings};
+
+pub const PROJECT_FILE_NAME: &str = "golden.toml";
+
+// The golden.toml file as it is written
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct ProjectFile {
+	version: Option<String>,
+	sebek: Option<String>,
+	non_finite: Option<toml::Value>,
+	no_brainfuck: Option<bool>,
+	input: Option<PathBuf>,
+	step_limit: Option<u64>,
+	#[serde(default)]
+	warnings: BTreeMap<String, String>,
+}
+
+// The settings of the project in the directory of the code, they apply unless the # statements or the flags set them too
+#[derive(Clone, Debug, Default)]
+pub struct Project {
+	pub path: Option<PathBuf>,
+
+	pub version: Option<String>,
+	pub sebek: [Option<f64>; 3],
+	pub non_finite: Option<NonFinitePolicy>,
+	pub no_brainfuck: bool,
+	pub input_path: Option<PathBuf>,
+	pub step_limit: Option<u64>,
+	pub warnings: Warnings,
+}
+
+impl Project {
+	// The golden.toml file next to the code, or the default settings without one
+	pub fn load(code_path: &Path) -> Result<Self, String> {
+		let directory = code_path.parent().unwrap_or_else(|| Path::new(""));
+		let path = directory.join(PROJECT_FILE_NAME);
+		if !path.is_file() {
+			return Ok(Self::default());
+		}
+		let text = std::fs::read_to_string(&path).map_err(|e| format!("Couldn't open {:?}: {}", path, e))?;
+		Self::parse(&text, &path).map_err(|e| format!("Invalid {:?}: {}", path, e))
+	}
+
+	fn parse(text: &str, path: &Path) -> Result<Self, String> {
+		let file = toml::from_str::<ProjectFile>(text).map_err(|e| e.to_string())?;
+		let non_finite = match file.non_finite {
+			None => None,
+			Some(toml::Value::String(policy)) => Some(Utils::parse_non_finite(&policy)?),
+			Some(toml::Value::Integer(number)) => Some(NonFinitePolicy::Replace(number as f64)),
+			Some(toml::Value::Float(number)) if number.is_finite() => Some(NonFinitePolicy::Replace(number)),
+			Some(value) => return Err(format!("`non-finite` needs `error`, `saturate` or a finite number, not `{}`", value)),
+		};
+		let sebek = match file.sebek {
+			Some(sebek) => Utils::parse_sebek(&sebek).map_err(|e| format!("`sebek` needs up to 3 numbers separated by `|`: {}", e))?,
+			None => [None, None, None],
+		};
+		let mut warnings = Warnings::new();
+		for (name, level) in &file.warnings {
+			let level = match level.to_lowercase().as_str() {
+				"allow" => WarningLevel::Allow,
+				"warn" => WarningLevel::Warn,
+				"deny" => WarningLevel::Deny,
+				_ => return Err(format!("The warning `{}` needs `allow`, `warn` or `deny`, not `{}`", name, level)),
+			};
+			let kinds = Warning::parse(name).map_err(|suggestion| match suggestion {
+				Some(suggestion) => format!("Unknown warning `{}`, did you mean `{}`?", name, suggestion),
+				None => format!("Unknown warning `{}`", name),
+			})?;
+			for warning in kinds {
+				warnings.set(warning, level);
+			}
+		}
+		let directory = path.parent().unwrap_or_else(|| Path::new(""));
+		Ok(Self {
+			path: Some(path.to_path_buf()),
+
+			version: file.version,
+			sebek,
+			non_finite,
+			no_brainfuck: file.no_brainfuck.unwrap_or(false),
+			// Relative to the project file, not to the directory the interpreter is run from
+			input_path: file.input.map(|input| directory.join(input)),
+			step_limit: file.step_limit,
+			warnings,
+		})
+	}
+}
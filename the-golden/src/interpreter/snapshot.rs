@@ -0,0 +1,184 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use crate::interpreter::memory::{CallFrame, MemoryRow};
+use crate::{Diagnostic, Flags};
+
+static SNAPSHOT_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+	pub version: String,
+	pub code: String,
+	pub code_path: std::path::PathBuf,
+
+	pub program_pointer: usize,
+
+	pub on_local: bool,
+	pub loops: Vec<usize>,
+	pub memory: [MemoryRow; 2],
+	pub active_memory: usize,
+	pub local_memory: [MemoryRow; 2],
+	pub active_local_memory: usize,
+	#[serde(default)]
+	pub call_stack: Vec<CallFrame>,
+
+	pub input_cache: Option<String>,
+}
+
+impl Snapshot {
+	pub fn load(path: &std::path::Path) -> Result<Self, String> {
+		let contents = std::fs::read_to_string(path).map_err(|e| format!("Couldn't open the snapshot file {:?}: {}", path, e))?;
+		serde_json::from_str(&contents).map_err(|e| format!("Couldn't read the snapshot file {:?}: {}", path, e))
+	}
+
+	// A snapshot written by hand or by another version could point outside of the memory of this one
+	pub fn validate(&self, version: &str) -> Result<(), String> {
+		if self.version != version {
+			return Err(format!("The snapshot was saved by version {}, it can't be resumed by version {}", self.version, version));
+		}
+		if self.active_memory > 1 || self.active_local_memory > 1 || self.call_stack.iter().any(|frame| frame.active_local_memory > 1) {
+			return Err(String::from("The active memory row of the snapshot has to be 0 or 1"));
+		}
+		Ok(())
+	}
+
+	// The positions can only be checked once the code is parsed
+	pub fn validate_positions(program_pointer: usize, loops: &[usize], call_stack: &[CallFrame], program_length: usize) -> Result<(), String> {
+		if program_pointer > program_length {
+			return Err(format!("The position {} of the snapshot is past the end of the code ({} commands)", program_pointer, program_length));
+		}
+		let mut positions = loops.iter().chain(call_stack.iter().flat_map(|frame| std::iter::once(&frame.return_address).chain(&frame.loops)));
+		match positions.find(|position| **position >= program_length) {
+			Some(position) => Err(format!("The position {} of the snapshot is past the end of the code ({} commands)", position, program_length)),
+			None => Ok(()),
+		}
+	}
+
+	pub fn save(&self, path: &std::path::Path) -> Result<(), String> {
+		let contents = serde_json::to_string_pretty(self).map_err(|e| format!("Couldn't serialize the interpreter state: {}", e))?;
+		std::fs::write(path, contents).map_err(|e| format!("Couldn't write the snapshot file {:?}: {}", path, e))
+	}
+
+	// Ctrl+C will ask the runner to save a snapshot and stop instead of killing the process
+	pub fn listen_for_signal() -> Result<(), String> {
+		ctrlc::set_handler(|| SNAPSHOT_REQUESTED.store(true, Ordering::SeqCst)).map_err(|e| format!("Couldn't set up the Ctrl+C handler for snapshots: {}", e))
+	}
+
+	pub fn requested() -> bool {
+		SNAPSHOT_REQUESTED.load(Ordering::SeqCst)
+	}
+
+	pub fn stop(&self, flags: &Flags) {
+		let reason = if Self::requested() { "Execution interrupted" } else { "Step limit reached" };
+		match &flags.snapshot_path {
+			Some(path) => match self.save(path) {
+				Ok(()) => Diagnostic::info(format!("\n{}, saved the interpreter state to {:?} (you can continue with --resume {:?})", reason, path, path)).emit(),
+				Err(e) => Diagnostic::error("snapshot", format!("\n{}", e)).emit(),
+			},
+			None => Diagnostic::warning("snapshot", format!("\n{}, stopping the execution (use the --snapshot flag to save the interpreter state)", reason)).emit(),
+		}
+	}
+
+	// Saves the state the program ended in for --final-snapshot, no matter why it ended
+	pub fn finish(&self, flags: &Flags) {
+		if let Some(path) = &flags.final_snapshot_path {
+			if let Err(e) = self.save(path) {
+				Diagnostic::error("snapshot", format!("\n{}", e)).emit();
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Snapshot;
+	use crate::interpreter::memory::{CallFrame, MemoryRow};
+
+	fn snapshot() -> Snapshot {
+		let mut row = MemoryRow::new();
+		row.move_right(2).unwrap();
+		row.set(f64::INFINITY);
+		row.move_left(5).unwrap();
+		row.set(-1.5);
+		Snapshot {
+			version: String::from("0.5.0"),
+			code: String::from("|3|[<]"),
+			code_path: std::path::PathBuf::from("maumivu.au"),
+
+			program_pointer: 1,
+
+			on_local: false,
+			loops: vec![1],
+			memory: [row, MemoryRow::new()],
+			active_memory: 0,
+			local_memory: [MemoryRow::new(), MemoryRow::new()],
+			active_local_memory: 1,
+			call_stack: vec![],
+
+			input_cache: Some(String::from("4")),
+		}
+	}
+
+	#[test]
+	fn saved_snapshots_load_the_same() {
+		let path = std::env::temp_dir().join(format!("the-golden-snapshot-{}.json", std::process::id()));
+		snapshot().save(&path).unwrap();
+		let loaded = Snapshot::load(&path).unwrap();
+		std::fs::remove_file(&path).ok();
+		assert_eq!(loaded.version, "0.5.0");
+		assert_eq!(loaded.program_pointer, 1);
+		assert_eq!(loaded.loops, vec![1]);
+		assert_eq!(loaded.memory[0].cells(), vec![(-3, -1.5), (2, f64::INFINITY)]);
+		assert_eq!(loaded.memory[0].index(), 0);
+		assert_eq!(loaded.active_local_memory, 1);
+		assert_eq!(loaded.input_cache.as_deref(), Some("4"));
+	}
+
+	#[test]
+	fn broken_files_are_errors() {
+		let path = std::env::temp_dir().join(format!("the-golden-broken-snapshot-{}.json", std::process::id()));
+		std::fs::write(&path, "{\"version\": \"0.5.0\"}").unwrap();
+		assert!(Snapshot::load(&path).is_err());
+		std::fs::remove_file(&path).ok();
+		assert!(Snapshot::load(&path).is_err());
+	}
+
+	#[test]
+	fn snapshots_only_resume_with_their_version() {
+		assert!(snapshot().validate("0.5.0").is_ok());
+		assert!(snapshot().validate("0.6.0").unwrap_err().contains("saved by version 0.5.0"));
+	}
+
+	#[test]
+	fn active_memory_rows_are_checked() {
+		let mut invalid = snapshot();
+		invalid.active_memory = 2;
+		assert!(invalid.validate("0.5.0").is_err());
+		let mut invalid = snapshot();
+		invalid.call_stack.push(CallFrame {
+			return_address: 0,
+			on_local: false,
+			loops: vec![],
+			local_memory: [MemoryRow::new(), MemoryRow::new()],
+			active_local_memory: 7,
+		});
+		assert!(invalid.validate("0.5.0").is_err());
+	}
+
+	#[test]
+	fn positions_have_to_be_in_the_code() {
+		assert!(Snapshot::validate_positions(6, &[], &[], 6).is_ok());
+		assert!(Snapshot::validate_positions(7, &[], &[], 6).is_err());
+		assert!(Snapshot::validate_positions(0, &[2, 6], &[], 6).is_err());
+		let frame = CallFrame {
+			return_address: 9,
+			on_local: false,
+			loops: vec![],
+			local_memory: [MemoryRow::new(), MemoryRow::new()],
+			active_local_memory: 0,
+		};
+		assert!(Snapshot::validate_positions(0, &[], &[frame], 6).unwrap_err().contains("position 9"));
+	}
+}
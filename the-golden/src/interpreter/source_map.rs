@@ -0,0 +1,89 @@
+use std::path::PathBuf;
+
+// Where a macro was invoked from
+#[derive(Clone, Debug)]
+pub struct MacroExpansion {
+	pub name: String,
+	pub file_path: PathBuf,
+	pub line: usize,
+	pub column: usize,
+}
+
+// A part of the preprocessed code that comes from one place in one file
+#[derive(Clone, Debug)]
+pub struct SourceSegment {
+	pub start: usize,
+	pub file_path: PathBuf,
+	pub line: usize,
+	pub column: usize,
+	pub expansion: Option<MacroExpansion>,
+}
+
+// Maps positions in the preprocessed code back to the files (and lines and columns in them) they came from
+#[derive(Clone, Debug)]
+pub struct SourceMap {
+	pub segments: Vec<SourceSegment>,
+}
+
+impl Default for SourceMap {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl SourceMap {
+	pub fn new() -> Self {
+		Self { segments: vec![] }
+	}
+
+	pub fn push(&mut self, start: usize, file_path: PathBuf, line: usize, column: usize) {
+		self.push_segment(SourceSegment {
+			start,
+			file_path,
+			line,
+			column,
+			expansion: None,
+		});
+	}
+
+	pub fn push_expansion(&mut self, start: usize, file_path: PathBuf, line: usize, column: usize, expansion: MacroExpansion) {
+		self.push_segment(SourceSegment {
+			start,
+			file_path,
+			line,
+			column,
+			expansion: Some(expansion),
+		});
+	}
+
+	fn push_segment(&mut self, segment: SourceSegment) {
+		if let Some(last) = self.segments.last_mut() {
+			if last.start == segment.start {
+				*last = segment;
+				return;
+			}
+		}
+		self.segments.push(segment);
+	}
+
+	pub fn describe_expansion(&self, segment: usize) -> String {
+		match self.segments.get(segment).and_then(|segment| segment.expansion.as_ref()) {
+			Some(expansion) => format!(
+				" (in the expansion of the macro {} invoked at {}:{} in {:?})",
+				expansion.name,
+				expansion.line,
+				expansion.column,
+				expansion.file_path.file_name().unwrap_or_default()
+			),
+			None => String::new(),
+		}
+	}
+
+	// The line and column the text ends at if it starts at the given line and column
+	pub fn advance(text: &str, line: usize, column: usize) -> (usize, usize) {
+		match text.rfind('\n') {
+			Some(index) => (line + text.matches('\n').count(), text.len() - index),
+			None => (line, column + text.len()),
+		}
+	}
+}
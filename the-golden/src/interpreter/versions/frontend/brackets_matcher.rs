@@ -65,6 +65,14 @@ impl BracketsMatcher {
 		}
 	}
 
+	// The positions of the brackets that didn't get a pair in match_brackets
+	pub fn unmatched(&self, code: &[String]) -> Vec<usize> {
+		code.iter().enumerate().filter(|(i, command)| match self.bracket_keys.get(*command) {
+			Some(category) => !self.brackets.get(category).unwrap().contains_key(i),
+			None => false
+		}).map(|(i, _)| i).collect()
+	}
+
 	fn num_equals(&self, left: &String, right: &String) -> isize {
 		if self.bracket_keys.get(left) != self.bracket_keys.get(right) {
 			return 0;
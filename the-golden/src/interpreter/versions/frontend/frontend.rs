@@ -0,0 +1,44 @@
+use regex::Regex;
+
+#[path = "./brackets_matcher.rs"]
+mod brackets_matcher;
+pub use brackets_matcher::BracketsMatcher;
+#[path = "./lexer.rs"]
+mod lexer;
+pub use lexer::Lexer;
+#[path = "./parser.rs"]
+mod parser;
+pub use parser::Parser;
+#[path = "./validator.rs"]
+mod validator;
+pub use validator::Validator;
+
+// A lexed command with the line, column and file it came from
+pub type Token = (String, usize, usize, std::path::PathBuf);
+
+// Everything the lexer, parser and validator need to know about the syntax of a version
+#[derive(Clone, Debug)]
+pub struct Grammar {
+	// Tried in order, the first one matching the start of the remaining code wins
+	pub rules: Vec<Regex>,
+	// Versions before 0.4.0 require the code to end with a ':'
+	pub line_end_required: bool,
+}
+
+impl Grammar {
+	pub fn check_line_end(&self, last: Option<&Token>) -> Result<(), String> {
+		if !self.line_end_required {
+			return Ok(());
+		}
+		match last {
+			Some((command, line, column, file_path)) if !command.contains(':') => Err(format!(
+				"Syntax error at {}:{} in {:?} ({:?}) - ':' expected",
+				line,
+				column,
+				file_path.file_name().unwrap(),
+				file_path.as_path()
+			)),
+			_ => Ok(()),
+		}
+	}
+}
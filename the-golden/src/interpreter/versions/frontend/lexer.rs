@@ -0,0 +1,127 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::{Grammar, Token};
+use crate::interpreter::source_map::SourceMap;
+
+lazy_static! {
+	static ref COMMENT_REGEX: Regex = Regex::new("^\"").unwrap();
+	static ref NEW_LINE_REGEX: Regex = Regex::new(r"^\r?\n").unwrap();
+}
+
+#[derive(Clone)]
+pub struct Lexer {
+	text: String,
+	grammar: Grammar,
+	line: usize,
+	column: usize,
+	comment: bool,
+	file_path: std::path::PathBuf,
+	position: usize,
+	source_map: SourceMap,
+	segment: usize,
+}
+
+impl Lexer {
+	pub fn new(text: String, grammar: Grammar, source_map: SourceMap) -> Self {
+		let first_segment = &source_map.segments[0];
+		Self {
+			text,
+			grammar,
+			line: first_segment.line,
+			column: first_segment.column,
+			comment: false,
+			file_path: first_segment.file_path.clone(),
+			position: 0,
+			source_map,
+			segment: 0,
+		}
+	}
+
+	// A #! line at the start only tells the system how to run the file as a script, so it isn't a part of the code
+	pub fn split_shebang(code: &str) -> (&str, &str) {
+		if !code.starts_with("#!") {
+			return ("", code);
+		}
+		code.split_at(code.find('\n').map(|end| end + 1).unwrap_or(code.len()))
+	}
+
+	// The file, line and column the next command would start at
+	pub fn location(&self) -> (&std::path::Path, usize, usize) {
+		(&self.file_path, self.line, self.column)
+	}
+
+	// Jump to the file and position the code at the current position came from
+	fn sync_segment(&mut self) {
+		while let Some(next_segment) = self.source_map.segments.get(self.segment + 1) {
+			if next_segment.start > self.position {
+				break;
+			}
+			self.segment += 1;
+			self.line = next_segment.line;
+			self.column = next_segment.column;
+			self.file_path = next_segment.file_path.clone();
+		}
+	}
+
+	// Moves past a character no rule matched, so the code after a syntax error can still be lexed
+	pub fn skip_char(&mut self) {
+		if let Some(skipped) = self.text[self.position..].chars().next() {
+			self.position += skipped.len_utf8();
+			if skipped == '\n' {
+				self.line += 1;
+				self.column = 1;
+			} else {
+				self.column += 1;
+			}
+		}
+	}
+
+	pub fn grammar(&self) -> &Grammar {
+		&self.grammar
+	}
+
+	pub fn next(&mut self) -> Result<Option<Token>, String> {
+		self.sync_segment();
+		let text = &self.text.as_str()[self.position..];
+		if text.is_empty() {
+			return Ok(None);
+		}
+		if text == "\"" {
+			self.comment = !self.comment;
+		}
+		if self.comment {
+			return Ok(None);
+		}
+		for rule in &self.grammar.rules {
+			if let Some(captures) = rule.captures(text) {
+				if let Some(capture) = captures.get(0) {
+					let (command_line, command_column) = (self.line, self.column);
+					let command = capture.as_str();
+					let command_length = capture.end() - capture.start();
+					self.position += command_length;
+					if command.contains('\n') {
+						self.line += command.matches('\n').count();
+						self.column = command.split('\n').next_back().unwrap().len() + 1;
+					} else {
+						self.column += command_length;
+					}
+					return Ok(Some((command.to_string(), command_line, command_column, self.file_path.clone())));
+				}
+			}
+		}
+		Err(format!(
+			"Syntax error at {}:{} in {:?} ({:?}){}",
+			self.line,
+			self.column,
+			self.file_path.file_name().unwrap(),
+			self.file_path.as_path(),
+			self.expansion()
+		))
+	}
+
+	// The macro the command just lexed was expanded from, if any
+	pub fn expansion(&self) -> String {
+		self.source_map.describe_expansion(self.segment)
+	}
+}
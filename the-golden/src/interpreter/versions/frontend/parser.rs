@@ -0,0 +1,103 @@
+use super::Token;
+
+pub struct Parser {
+	pub commands: Vec<String>,
+	pub commands_info: Vec<Token>,
+	pub commands_expansions: Vec<String>,
+}
+
+impl Parser {
+	pub fn new() -> Self {
+		Self {
+			commands: vec![],
+			commands_info: vec![],
+			commands_expansions: vec![],
+		}
+	}
+
+	pub fn run(&mut self, mut lexer: super::Lexer) -> Result<u8, String> {
+		let mut t = lexer.next();
+		let mut last = None;
+		while t.is_ok() && t.clone().unwrap().is_some() {
+			let val = t.clone().unwrap().unwrap();
+			let (command, ..) = val.clone();
+			if !((command.starts_with('"') && command.ends_with('"')) || command.contains(':')) {
+				self.commands.push(command);
+				self.commands_info.push(val.clone());
+				self.commands_expansions.push(lexer.expansion());
+			}
+			last = Some(val);
+			t = lexer.next();
+		}
+		t?;
+		lexer.grammar().check_line_end(last.as_ref())?;
+		Ok(0)
+	}
+
+	// Skips the characters no command matches, so the rest of the code can still be checked after a syntax error
+	pub fn run_skipping_errors(&mut self, mut lexer: super::Lexer) {
+		loop {
+			match lexer.next() {
+				Ok(Some(token)) => {
+					if !((token.0.starts_with('"') && token.0.ends_with('"')) || token.0.contains(':')) {
+						self.commands.push(token.0.clone());
+						self.commands_info.push(token);
+						self.commands_expansions.push(lexer.expansion());
+					}
+				}
+				Ok(None) => break,
+				Err(_) => lexer.skip_char(),
+			}
+		}
+	}
+
+	// Where the command came from, including the call of the macro it was expanded from
+	pub fn location(&self, i: usize) -> String {
+		let (_, line, column, file_path) = &self.commands_info[i];
+		format!("at {}:{} in {:?}{}", line, column, file_path.file_name().unwrap_or_default(), self.commands_expansions[i])
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Parser;
+	use crate::interpreter::source_map::{MacroExpansion, SourceMap};
+	use crate::interpreter::versions_handler::frontend::Lexer;
+	use crate::interpreter::versions_handler::v0_6_0::grammar;
+	use std::path::PathBuf;
+
+	// "!" from maumivu.au, then ">." from a macro defined in lib.au and called at 1:2, then "<" after the call
+	fn expanded_code() -> (String, SourceMap) {
+		let mut source_map = SourceMap::new();
+		source_map.push(0, PathBuf::from("maumivu.au"), 1, 1);
+		let expansion = MacroExpansion {
+			name: String::from("NEXT"),
+			file_path: PathBuf::from("maumivu.au"),
+			line: 1,
+			column: 2,
+		};
+		source_map.push_expansion(1, PathBuf::from("lib.au"), 4, 14, expansion);
+		source_map.push(3, PathBuf::from("maumivu.au"), 1, 8);
+		(String::from("!>.<\n"), source_map)
+	}
+
+	#[test]
+	fn locations_point_into_the_macro_definition() {
+		let (code, source_map) = expanded_code();
+		let mut parser = Parser::new();
+		parser.run(Lexer::new(code, grammar(), source_map)).unwrap();
+		assert_eq!(parser.commands[..4], ["!", ">", ".", "<"]);
+		assert_eq!(parser.location(0), "at 1:1 in \"maumivu.au\"");
+		assert_eq!(parser.location(1), "at 4:14 in \"lib.au\" (in the expansion of the macro NEXT invoked at 1:2 in \"maumivu.au\")");
+		assert_eq!(parser.location(2), "at 4:15 in \"lib.au\" (in the expansion of the macro NEXT invoked at 1:2 in \"maumivu.au\")");
+		assert_eq!(parser.location(3), "at 1:8 in \"maumivu.au\"");
+	}
+
+	#[test]
+	fn syntax_errors_mention_the_macro() {
+		let (_, source_map) = expanded_code();
+		let error = Parser::new().run(Lexer::new(String::from("!>x<\n"), grammar(), source_map)).unwrap_err();
+		assert!(error.starts_with("Syntax error at 4:15 in \"lib.au\""));
+		assert!(error.ends_with("(in the expansion of the macro NEXT invoked at 1:2 in \"maumivu.au\")"));
+	}
+}
@@ -0,0 +1,60 @@
+use crate::Diagnostic;
+
+pub struct Validator {}
+
+impl Validator {
+	pub fn run(mut lexer: super::Lexer, heavy_debug: bool) -> Result<u8, Diagnostic> {
+		let mut t = lexer.next();
+		let mut last = None;
+		if heavy_debug {
+			Diagnostic::trace(format!("Matched command: {:?}", t)).emit();
+		}
+		while t.is_ok() && t.clone().unwrap().is_some() {
+			last = t.clone().unwrap();
+			t = lexer.next();
+			if heavy_debug {
+				Diagnostic::trace(format!("Matched command: {:?}", t)).emit();
+			}
+		}
+		if let Err(e) = t {
+			let (file_path, line, column) = lexer.location();
+			return Err(Diagnostic::error("syntax", e).at(file_path, line, column));
+		}
+		if let Err(e) = lexer.grammar().check_line_end(last.as_ref()) {
+			// Only the last command can be missing the ':'
+			let (_, line, column, file_path) = last.unwrap();
+			return Err(Diagnostic::error("syntax", e).at(&file_path, line, column));
+		}
+		Ok(0)
+	}
+
+	// Like `run`, but goes on after a syntax error to find all of them
+	pub fn run_all(mut lexer: super::Lexer) -> Vec<Diagnostic> {
+		let mut errors = vec![];
+		let mut last = None;
+		// Only the first of the characters in a row that no rule matches is reported
+		let mut skipping = false;
+		loop {
+			match lexer.next() {
+				Ok(Some(token)) => {
+					last = Some(token);
+					skipping = false;
+				}
+				Ok(None) => break,
+				Err(e) => {
+					if !skipping {
+						let (file_path, line, column) = lexer.location();
+						errors.push(Diagnostic::error("syntax", e).at(file_path, line, column));
+					}
+					skipping = true;
+					lexer.skip_char();
+				}
+			}
+		}
+		if let Err(e) = lexer.grammar().check_line_end(last.as_ref()) {
+			let (_, line, column, file_path) = last.unwrap();
+			errors.push(Diagnostic::error("syntax", e).at(&file_path, line, column));
+		}
+		errors
+	}
+}
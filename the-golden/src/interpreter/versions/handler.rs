@@ -1,5 +1,13 @@
-use crate::Flags;
+use crate::interpreter::source_map::SourceMap;
+use crate::interpreter::Snapshot;
+use crate::{Diagnostic, Flags};
 
+#[path = "./frontend/frontend.rs"]
+pub mod frontend;
+use frontend::Grammar;
+#[path = "./requirement.rs"]
+mod requirement;
+use requirement::Requirement;
 #[path = "./v0-1-0/main.rs"]
 mod v0_1_0;
 #[path = "./v0-2-0/main.rs"]
@@ -8,163 +16,122 @@ mod v0_2_0;
 mod v0_3_0;
 #[path = "./v0-4-0/main.rs"]
 mod v0_4_0;
+#[path = "./v0-5-0/main.rs"]
+mod v0_5_0;
+#[path = "./v0-6-0/main.rs"]
+mod v0_6_0;
+
+// An interpreter version that can be selected with --version or #version
+pub trait Version {
+	fn version(&self) -> &'static str;
+	fn name(&self) -> &'static str;
+	fn changelog(&self) -> &'static [&'static str];
+	fn grammar(&self) -> Grammar;
+	fn runner(&self, code: String, source_map: SourceMap, flags: Flags) -> Box<dyn Runner>;
+
+	// Problems with the parsed commands that only this version knows about (the commands and their positions come from the parser)
+	fn check(&self, _parser: &frontend::Parser) -> Vec<Diagnostic> {
+		vec![]
+	}
+
+	fn semver(&self) -> [u64; 3] {
+		let mut semver = [0; 3];
+		for (part, value) in semver.iter_mut().zip(self.version().split('.')) {
+			*part = value.parse().unwrap_or(0);
+		}
+		semver
+	}
+}
+
+pub trait Runner {
+	// Returns the exit code of the process
+	fn run(&mut self) -> i32;
+	fn restore(&mut self, snapshot: Snapshot) -> Result<(), String>;
+	fn snapshot(&self) -> Snapshot;
+}
 
 pub struct Handler {
-	versions: Versions,
+	versions: Vec<Box<dyn Version>>,
 }
 
 impl Handler {
 	pub fn new() -> Self {
-		let versions_0 = Version::new(
-			String::from("0"),
-			vec![
-				Version::new(String::from("1"), vec![Version::new(String::from("0"), vec![])]),
-				Version::new(String::from("2"), vec![Version::new(String::from("0"), vec![])]),
-				Version::new(String::from("3"), vec![Version::new(String::from("0"), vec![])]),
-				Version::new(String::from("4"), vec![Version::new(String::from("0"), vec![])]),
-			],
-		);
-		let versions = Versions::new(vec![versions_0]);
-		Self { versions }
+		let mut handler = Self { versions: vec![] };
+		handler.register(Box::new(v0_1_0::Info));
+		handler.register(Box::new(v0_2_0::Info));
+		handler.register(Box::new(v0_3_0::Info));
+		handler.register(Box::new(v0_4_0::Info));
+		handler.register(Box::new(v0_5_0::Info));
+		handler.register(Box::new(v0_6_0::Info));
+		handler
 	}
 
-	pub fn parse_version(&self, mut version: String, ansi_enabled: bool) -> String {
-		let version_original = version.clone();
-		if version.to_lowercase() == *"latest" {
-			version = "x.x.x".to_string();
-		}
-		let mut parts = version.split('.').map(|x| x.to_string()).collect::<Vec<String>>();
-		parts.truncate(3);
-		for _ in 0..3 - parts.len() {
-			parts.push(String::from("x"));
-		}
-		let s = parts[2].split('-').map(|x| x.to_string()).collect::<Vec<String>>();
-		let (parts, prerelease, _build_metadata) = if s.len() > 1 {
-			let s_joined = s[1..].join("-");
-			let p = s_joined.clone();
-			let s2 = p.split('+').map(|x| x.to_string()).collect::<Vec<String>>();
-			if s2.len() > 1 {
-				(vec![parts[0].clone(), parts[1].clone(), s[0].clone()], Some(s2[0].clone()), Some(s2[1..].join("+")))
-			} else {
-				(vec![parts[0].clone(), parts[1].clone(), s[0].clone()], Some(s_joined), None)
-			}
-		} else {
-			(parts, None, None)
-		};
-		let mut version_parsed: Vec<String> = vec![];
-		let mut current_subversion = if parts[0].parse::<i32>().is_err() {
-			self.versions.versions.last().unwrap()
-		} else {
-			let mut to_return: Option<&Version> = None;
-			for subversion in &self.versions.versions {
-				if subversion.value == parts[0] {
-					to_return = Some(subversion);
-					break;
-				}
-			}
-			if let Some(to_return) = to_return {
-				to_return
-			} else {
-				self.versions.versions.last().unwrap()
-			}
-		};
-		version_parsed.push(current_subversion.value.clone());
-		for ver in &parts[1..3] {
-			if ver.parse::<i32>().is_err() {
-				current_subversion = current_subversion.sub.last().unwrap();
-			} else {
-				let mut to_return: Option<&Version> = None;
-				for subversion in &current_subversion.sub {
-					if subversion.value == *ver {
-						to_return = Some(subversion);
-						break;
-					}
-				}
-				current_subversion = if let Some(to_return) = to_return { to_return } else { current_subversion.sub.last().unwrap() }
+	pub fn register(&mut self, version: Box<dyn Version>) {
+		self.versions.push(version);
+		self.versions.sort_by_key(|version| version.semver());
+	}
+
+	fn get(&self, version: &str) -> Option<&dyn Version> {
+		self.versions.iter().find(|registered| registered.version() == version).map(|registered| registered.as_ref())
+	}
+
+	pub fn grammar(&self, version: &str) -> Option<Grammar> {
+		self.get(version).map(|registered| registered.grammar())
+	}
+
+	pub fn check(&self, version: &str, parser: &frontend::Parser) -> Vec<Diagnostic> {
+		self.get(version).map(|registered| registered.check(parser)).unwrap_or_default()
+	}
+
+	pub fn print_versions(&self, ansi_enabled: bool) {
+		let latest = self.versions.last().map(|version| version.version());
+		for version in self.versions.iter().rev() {
+			let label = if Some(version.version()) == latest { " (latest)" } else { "" };
+			println!("{}{}{}", crate::Utils::ansi_escape_text("92", version.version(), crate::INFO_PREFIX_LENGTH, ansi_enabled), version.name(), label);
+			for change in version.changelog() {
+				println!("{}- {}", " ".repeat(crate::INFO_PREFIX_LENGTH), change);
 			}
-			version_parsed.push(current_subversion.value.clone());
 		}
-		let prerelease = match (prerelease, !current_subversion.sub.is_empty()) {
-			(Some(ver), true) => {
-				let mut to_return: Option<&Version> = None;
-				for subversion in &current_subversion.sub {
-					if subversion.value == ver {
-						to_return = Some(subversion);
-						break;
-					}
-				}
-				current_subversion = if let Some(to_return) = to_return { to_return } else { current_subversion.sub.last().unwrap() };
-				format!("-{}", current_subversion.value.clone())
-			}
-			_ => String::new(),
-		};
-		let version_final = format!("{}{}", version_parsed.join("."), prerelease);
-		if version_original != version_final && version_original.to_lowercase() != "latest" {
-			println!(
-				"{}Could not find version {}, instead found {}",
-				crate::Utils::ansi_escape_text("93", "WARNING", v0_1_0::INFO_PREFIX_LENGTH, ansi_enabled),
-				version_original,
-				version_final
-			);
+	}
+
+	// Finds the newest version that matches the requirement (`latest`, `0.4.0`, `0.4.x`, `^0.3`, `>=0.2, <0.4`, ...)
+	pub fn resolve_version(&self, requirement: String) -> Result<String, String> {
+		let parsed = Requirement::parse(&requirement)?;
+		match self.versions.iter().rev().find(|version| parsed.matches(version.semver())) {
+			Some(found) => Ok(found.version().to_string()),
+			None => Err(format!(
+				"No version matches `{}` - the available versions are {}",
+				requirement,
+				self.versions.iter().rev().map(|version| version.version()).collect::<Vec<&str>>().join(", ")
+			)),
 		}
-		version_final
 	}
 
-	pub fn run(&self, version: String, code: String, code_path: std::path::PathBuf, flags: Flags, ansi_enabled: bool) {
-		match version.as_str() {
-			"0.1.0" => {
-				if flags.debug {
-					println!("{}Running version {}", crate::Utils::ansi_escape_text("94", "DEBUG", v0_1_0::INFO_PREFIX_LENGTH, ansi_enabled), version);
-				};
-				v0_1_0::Runner::new(code, code_path, flags, ansi_enabled).run()
-			}
-			"0.2.0" => {
-				if flags.debug {
-					println!("{}Running version {}", crate::Utils::ansi_escape_text("94", "DEBUG", v0_2_0::INFO_PREFIX_LENGTH, ansi_enabled), version);
-				};
-				v0_2_0::Runner::new(code, code_path, flags, ansi_enabled).run()
-			}
-			"0.3.0" => {
-				if flags.debug {
-					println!("{}Running version {}", crate::Utils::ansi_escape_text("94", "DEBUG", v0_3_0::INFO_PREFIX_LENGTH, ansi_enabled), version);
-				};
-				v0_3_0::Runner::new(code, code_path, flags, ansi_enabled).run()
-			}
-			"0.4.0" => {
+	pub fn run(&self, version: String, code: String, source_map: SourceMap, flags: Flags, snapshot: Option<Snapshot>) -> i32 {
+		self.run_keeping_state(version, code, source_map, flags, snapshot).0
+	}
+
+	// Also returns the state the runner ended in, so the next code can continue from it (None if the code didn't start)
+	pub fn run_keeping_state(&self, version: String, code: String, source_map: SourceMap, flags: Flags, snapshot: Option<Snapshot>) -> (i32, Option<Snapshot>) {
+		match self.get(&version) {
+			Some(registered) => {
 				if flags.debug {
-					println!("{}Running version {}", crate::Utils::ansi_escape_text("94", "DEBUG", v0_4_0::INFO_PREFIX_LENGTH, ansi_enabled), version);
+					Diagnostic::debug(format!("Running version {}", version)).emit();
 				};
-				v0_4_0::Runner::new(code, code_path, flags, ansi_enabled).run()
+				let mut runner = registered.runner(code, source_map, flags);
+				if let Some(snapshot) = snapshot {
+					if let Err(e) = runner.restore(snapshot) {
+						Diagnostic::error("snapshot", e).emit();
+						return (crate::EXIT_IO_ERROR, None);
+					}
+				}
+				let exit_code = runner.run();
+				(exit_code, Some(runner.snapshot()))
 			}
-			_ => {
-				println!(
-					"{}Couldn't run version {}",
-					crate::Utils::ansi_escape_text("91", "ERROR", v0_1_0::INFO_PREFIX_LENGTH, ansi_enabled),
-					version
-				);
+			None => {
+				Diagnostic::error("version", format!("Couldn't run version {}", version)).emit();
+				(crate::EXIT_USAGE_ERROR, None)
 			}
 		}
 	}
 }
-
-struct Versions {
-	versions: Vec<Version>,
-}
-
-impl Versions {
-	fn new(versions: Vec<Version>) -> Self {
-		Self { versions }
-	}
-}
-
-#[derive(Debug)]
-struct Version {
-	sub: Vec<Version>,
-	value: String,
-}
-
-impl Version {
-	fn new(value: String, sub: Vec<Version>) -> Self {
-		Self { sub, value }
-	}
-}
@@ -0,0 +1,159 @@
+// A semver requirement like `0.4.0`, `0.4.x`, `^0.3`, `~0.4.0` or `>=0.2, <0.4`
+// Every comparator is turned into the range of versions it allows, and a version matches if it's in all of them
+#[derive(Clone, Debug)]
+pub struct Requirement {
+	ranges: Vec<([u64; 3], Option<[u64; 3]>)>,
+}
+
+impl Requirement {
+	pub fn parse(requirement: &str) -> Result<Self, String> {
+		let requirement = requirement.trim();
+		if requirement.is_empty() || requirement.to_lowercase() == "latest" || requirement == "*" {
+			return Ok(Self { ranges: vec![([0; 3], None)] });
+		}
+		let mut ranges = vec![];
+		for comparator in requirement.split(',') {
+			let comparator = comparator.trim();
+			let split = comparator.find(|character: char| character.is_ascii_digit() || "xX*".contains(character)).unwrap_or(comparator.len());
+			let (operator, version) = (comparator[..split].trim(), comparator[split..].trim());
+			let parts = Self::parse_parts(version).ok_or_else(|| format!("Invalid version requirement `{}` - use something like `0.4.0`, `^0.3` or `>=0.2, <0.4`", comparator))?;
+			ranges.push(Self::range(operator, &parts).ok_or_else(|| format!("Unknown operator `{}` in the version requirement `{}`", operator, comparator))?);
+		}
+		Ok(Self { ranges })
+	}
+
+	// The numbers before the first wildcard or missing part (pre-release and build suffixes are ignored)
+	fn parse_parts(version: &str) -> Option<Vec<u64>> {
+		let core = version.split(['-', '+']).next().unwrap_or_default();
+		let mut parts = vec![];
+		let mut wildcard = false;
+		for (index, part) in core.split('.').enumerate() {
+			if index >= 3 {
+				return None;
+			}
+			if ["x", "X", "*"].contains(&part) {
+				wildcard = true;
+			} else if wildcard {
+				return None;
+			} else {
+				parts.push(part.parse::<u64>().ok()?);
+			}
+		}
+		Some(parts)
+	}
+
+	// The lowest allowed version and the first version that isn't allowed anymore
+	fn range(operator: &str, parts: &[u64]) -> Option<([u64; 3], Option<[u64; 3]>)> {
+		let mut lowest = [0; 3];
+		lowest[..parts.len()].copy_from_slice(parts);
+		// The first version after all the versions the partial one stands for, if there is one
+		let after = |level: usize| -> Option<[u64; 3]> {
+			let mut version = [0; 3];
+			version[..level].copy_from_slice(&parts[..level]);
+			if level == 0 {
+				return None;
+			}
+			// A part that can't grow any further leaves no upper bound
+			version[level - 1] = version[level - 1].checked_add(1)?;
+			Some(version)
+		};
+		let range = match operator {
+			"" | "=" => (lowest, after(parts.len())),
+			"^" => {
+				// Everything up to the first part that isn't zero has to stay the same
+				let level = parts.iter().position(|part| *part != 0).map(|position| position + 1).unwrap_or(parts.len());
+				(lowest, after(level))
+			}
+			// Only the patch version can change, or the minor one too if it wasn't given
+			"~" => (lowest, after(parts.len().min(2))),
+			">=" => (lowest, None),
+			">" => match after(parts.len()) {
+				Some(next) => (next, None),
+				// Nothing is greater than the largest version
+				None if !parts.is_empty() => ([u64::MAX; 3], Some([u64::MAX; 3])),
+				None => return None,
+			},
+			"<" => ([0; 3], Some(lowest)),
+			"<=" => ([0; 3], after(parts.len())),
+			_ => return None,
+		};
+		Some(range)
+	}
+
+	pub fn matches(&self, version: [u64; 3]) -> bool {
+		self.ranges.iter().all(|(lowest, end)| version >= *lowest && end.is_none_or(|end| version < end))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Requirement;
+
+	fn matches(requirement: &str, version: [u64; 3]) -> bool {
+		Requirement::parse(requirement).unwrap().matches(version)
+	}
+
+	#[test]
+	fn caret_keeps_the_first_non_zero_part() {
+		assert!(matches("^0.3", [0, 3, 0]));
+		assert!(matches("^0.3", [0, 3, 7]));
+		assert!(!matches("^0.3", [0, 4, 0]));
+		assert!(matches("^0.0.3", [0, 0, 3]));
+		assert!(!matches("^0.0.3", [0, 0, 4]));
+		assert!(matches("^1.2", [1, 9, 0]));
+		assert!(!matches("^1.2", [2, 0, 0]));
+		assert!(!matches("^1.2", [1, 1, 0]));
+	}
+
+	#[test]
+	fn tilde_only_changes_the_patch_version() {
+		assert!(matches("~0.4.0", [0, 4, 9]));
+		assert!(!matches("~0.4.0", [0, 5, 0]));
+		assert!(matches("~0", [0, 9, 0]));
+		assert!(!matches("~0", [1, 0, 0]));
+	}
+
+	#[test]
+	fn wildcards_and_partial_versions() {
+		assert!(matches("0.4.x", [0, 4, 2]));
+		assert!(!matches("0.4.x", [0, 5, 0]));
+		assert!(matches("0.*", [0, 6, 0]));
+		assert!(matches("*", [0, 1, 0]));
+		assert!(matches("0.4", [0, 4, 3]));
+		assert!(matches("0.4.0", [0, 4, 0]));
+		assert!(!matches("0.4.0", [0, 4, 1]));
+		assert!(Requirement::parse("0.x.1").is_err());
+		assert!(Requirement::parse("0.4.0.1").is_err());
+	}
+
+	#[test]
+	fn comma_joined_ranges_all_have_to_match() {
+		assert!(matches(">=0.2, <0.4", [0, 2, 0]));
+		assert!(matches(">=0.2, <0.4", [0, 3, 5]));
+		assert!(!matches(">=0.2, <0.4", [0, 4, 0]));
+		assert!(!matches(">=0.2, <0.4", [0, 1, 0]));
+		assert!(matches(">0.2, <=0.4", [0, 4, 9]));
+		assert!(!matches(">0.2, <=0.4", [0, 2, 9]));
+	}
+
+	#[test]
+	fn latest_matches_everything() {
+		assert!(matches("latest", [0, 1, 0]));
+		assert!(matches("LATEST", [99, 0, 0]));
+		assert!(matches("", [0, 6, 0]));
+	}
+
+	#[test]
+	fn nothing_matches() {
+		assert!(!matches("^0.9", [0, 6, 0]));
+		assert!(!matches(">=0.4, <0.3", [0, 3, 5]));
+		assert!(Requirement::parse("!0.4").is_err());
+		assert!(Requirement::parse("abc").is_err());
+	}
+
+	#[test]
+	fn overflowing_parts_have_no_upper_bound() {
+		assert!(matches("~0.18446744073709551615", [0, u64::MAX, 7]));
+		assert!(matches("<=18446744073709551615", [u64::MAX, u64::MAX, 0]));
+	}
+}
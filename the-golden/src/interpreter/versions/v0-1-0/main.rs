@@ -1,51 +1,21 @@
 use std::collections::HashMap;
 
+use crate::interpreter::memory::MemoryRow;
+use crate::interpreter::snapshot::Snapshot;
+use crate::interpreter::source_map::SourceMap;
 use crate::Flags;
+use rand::rngs::StdRng;
 use rand::Rng;
 use regex::Regex;
 
-#[path = "./brackets_matcher.rs"]
-mod brackets_matcher;
-use brackets_matcher::BracketsMatcher;
-#[path = "./lexer.rs"]
-mod lexer;
-pub use lexer::Lexer;
-#[path = "./parser.rs"]
-mod parser;
-use crate::Utils;
-pub use parser::Parser;
-#[path = "./validator.rs"]
-mod validator;
-use validator::Validator;
-
-pub const INFO_PREFIX_LENGTH: usize = 12;
+use super::frontend::{BracketsMatcher, Grammar, Lexer, Parser, Validator};
+use crate::{Diagnostic, FoundWarnings, Utils, Warning};
 
-pub struct Runner {
-	flags: Flags,
-	ansi_enabled: bool,
-
-	brackets_matcher: BracketsMatcher,
-	brackets_categorised: HashMap<String, HashMap<usize, usize>>,
-	opposite_commands: HashMap<String, String>,
-
-	brackets: HashMap<usize, usize>,
-	raw_code: String,
-	rules: Vec<Regex>,
-	code_path: std::path::PathBuf,
-
-	program_pointer: usize,
+pub const VERSION: &str = "0.1.0";
 
-	loops: Vec<usize>,
-	memory: [Vec<f64>; 2],
-	memory_pointers: [usize; 2],
-	active_memory: usize,
-
-	input_cache: Option<String>,
-}
-
-impl Runner {
-	pub fn new(raw_code: String, code_path: std::path::PathBuf, flags: Flags, ansi_enabled: bool) -> Self {
-		let rules = vec![
+pub fn grammar() -> Grammar {
+	Grammar {
+		rules: vec![
 			Regex::new(r"^'?(\|-?[0-9]*\|)*!").unwrap(),   // increment
 			Regex::new(r"^'?(\|-?[0-9]*\|)*~").unwrap(),   // decrement
 			Regex::new(r"^'?(\|-?[0-9]*\|)*\+").unwrap(),  // add
@@ -71,10 +41,72 @@ impl Runner {
 			Regex::new(r"^:\r?\n?").unwrap(),              // end of line
 			Regex::new("^\"[^\"]*\"").unwrap(),            // comments
 			Regex::new(r"^[ \t\f\v]").unwrap(),            // whitespace
-		];
+		],
+		line_end_required: true,
+	}
+}
+
+pub struct Info;
+
+impl super::Version for Info {
+	fn version(&self) -> &'static str {
+		VERSION
+	}
+
+	fn name(&self) -> &'static str {
+		"Initial release"
+	}
+
+	fn changelog(&self) -> &'static [&'static str] {
+		&[
+			"The memory commands, loops, breaks, input and output",
+			"Commands work on the local memory when prefixed with '",
+		]
+	}
+
+	fn grammar(&self) -> Grammar {
+		grammar()
+	}
+
+	fn runner(&self, code: String, source_map: SourceMap, flags: Flags) -> Box<dyn super::Runner> {
+		Box::new(Runner::new(code, self.grammar(), source_map, flags))
+	}
+}
+
+pub struct Runner {
+	flags: Flags,
+
+	brackets_matcher: BracketsMatcher,
+	brackets_categorised: HashMap<String, HashMap<usize, usize>>,
+	opposite_commands: HashMap<String, String>,
+
+	brackets: HashMap<usize, usize>,
+	raw_code: String,
+	grammar: Grammar,
+	code_path: std::path::PathBuf,
+	source_map: SourceMap,
+
+	program_pointer: usize,
+
+	loops: Vec<usize>,
+	memory: [MemoryRow; 2],
+	active_memory: usize,
+	local_memory: [MemoryRow; 2],
+	active_local_memory: usize,
+
+	input_cache: Option<String>,
+	rng: StdRng,
+	exit_code: Option<i32>,
+
+	// Found by the last command, the run loop prints them with its location
+	warnings_found: FoundWarnings,
+}
+
+impl Runner {
+	pub fn new(raw_code: String, grammar: Grammar, source_map: SourceMap, flags: Flags) -> Self {
+		let seed = flags.seed;
 		Self {
 			flags,
-			ansi_enabled,
 
 			brackets_matcher: BracketsMatcher::new(),
 
@@ -92,54 +124,58 @@ impl Runner {
 			]),
 
 			raw_code,
-			rules,
-			code_path,
+			grammar,
+			code_path: source_map.segments[0].file_path.clone(),
+			source_map,
 
 			program_pointer: 0,
 
 			loops: vec![],
-			memory: [vec![0.0], vec![0.0]],
-			memory_pointers: [0, 0],
+			memory: [MemoryRow::new(), MemoryRow::new()],
 			active_memory: 0,
+			local_memory: [MemoryRow::new(), MemoryRow::new()],
+			active_local_memory: 0,
 
 			input_cache: None,
+			rng: Utils::rng(seed),
+			exit_code: None,
+			warnings_found: FoundWarnings::new(),
 		}
 	}
 
-	pub fn run(&mut self) {
+	pub fn run(&mut self) -> i32 {
 		if self.flags.debug {
-			println!("{}Raw code: {}", Utils::ansi_escape_text("94", "DEBUG", INFO_PREFIX_LENGTH, self.ansi_enabled), self.raw_code);
+			Diagnostic::debug(format!("Raw code: {}", self.raw_code)).emit();
 		}
-		let lexer = Lexer::new(self.raw_code.clone(), self.rules.clone(), self.code_path.clone());
-		let validator_result = Validator::run(lexer.clone(), self.flags.debug_heavy, self.ansi_enabled);
+		let lexer = Lexer::new(self.raw_code.clone(), self.grammar.clone(), self.source_map.clone());
+		let validator_result = Validator::run(lexer.clone(), self.flags.debug_heavy);
 		if let Err(e) = validator_result {
-			println!("{}{}", Utils::ansi_escape_text("91", "ERROR", INFO_PREFIX_LENGTH, self.ansi_enabled), e);
-			return;
+			e.emit();
+			return crate::EXIT_SYNTAX_ERROR;
 		}
 		if self.flags.debug {
-			println!("{}Valid code!", Utils::ansi_escape_text("94", "DEBUG", INFO_PREFIX_LENGTH, self.ansi_enabled));
+			Diagnostic::debug("Valid code!").emit();
 		}
 		let mut parser = Parser::new();
 		let parser_result = parser.run(lexer);
 		if let Err(e) = parser_result {
-			println!("{}{}", Utils::ansi_escape_text("91", "ERROR", INFO_PREFIX_LENGTH, self.ansi_enabled), e);
-			return;
+			Diagnostic::error("syntax", e).emit();
+			return crate::EXIT_SYNTAX_ERROR;
 		}
 		if self.flags.debug {
-			println!(
-				"{}Parsed commands: {:?}",
-				Utils::ansi_escape_text("94", "DEBUG", INFO_PREFIX_LENGTH, self.ansi_enabled),
-				parser.commands
-			);
+			Diagnostic::debug(format!("Parsed commands: {:?}", parser.commands)).emit();
 		}
 		self.brackets_matcher.match_brackets(&parser.commands);
 		self.brackets_categorised = self.brackets_matcher.brackets.clone();
+		if let Some(&i) = self.brackets_matcher.unmatched(&parser.commands).first() {
+			let (command, line, column, file_path) = &parser.commands_info[i];
+			Diagnostic::error("unmatched-bracket", format!("`{}` without a matching bracket {}", command, parser.location(i)))
+				.at(file_path, *line, *column)
+				.emit();
+			return crate::EXIT_SYNTAX_ERROR;
+		}
 		if self.flags.debug_heavy {
-			println!(
-				"{}Matched brackets: {:?}",
-				Utils::ansi_escape_text("34", "HEAVY DEBUG", INFO_PREFIX_LENGTH, self.ansi_enabled),
-				self.brackets_matcher.brackets
-			);
+			Diagnostic::trace(format!("Matched brackets: {:?}", self.brackets_matcher.brackets)).emit();
 		}
 		for loop_type in self.brackets_categorised.keys() {
 			let map = self.brackets_categorised.get(loop_type).unwrap();
@@ -148,64 +184,77 @@ impl Runner {
 			}
 		}
 		if self.flags.debug_heavy {
-			println!(
-				"{}Matched brackets uncategorised: {:?}",
-				Utils::ansi_escape_text("34", "HEAVY DEBUG", INFO_PREFIX_LENGTH, self.ansi_enabled),
-				self.brackets
-			);
+			Diagnostic::trace(format!("Matched brackets uncategorised: {:?}", self.brackets)).emit();
 		}
 		if self.flags.debug {
-			println!("{}----- START OF CODE EXECUTION -----", Utils::ansi_escape_text("94", "DEBUG", INFO_PREFIX_LENGTH, self.ansi_enabled));
+			Diagnostic::debug("----- START OF CODE EXECUTION -----").emit();
 		}
-		let mut local_memory: [Vec<f64>; 2] = [vec![0.0], vec![0.0]];
-		let mut local_memory_pointers: [usize; 2] = [0, 0];
-		let mut active_local_memory: usize = 0;
 		let program_length = parser.commands.len();
+		if let Err(e) = Snapshot::validate_positions(self.program_pointer, &self.loops, &[], program_length) {
+			Diagnostic::error("snapshot", e).emit();
+			return crate::EXIT_IO_ERROR;
+		}
+		let mut steps: u64 = 0;
 		while self.program_pointer < program_length {
+			if Snapshot::requested() || self.flags.step_limit.is_some_and(|limit| steps >= limit) {
+				self.snapshot().stop(&self.flags);
+				self.exit_code = Some(crate::EXIT_STOPPED);
+				break;
+			}
 			let command = &parser.commands[self.program_pointer];
-			active_local_memory = match self.evaluate_command(command, &mut local_memory, &mut local_memory_pointers, active_local_memory) {
-				Ok(val) => val,
-				Err(e) => {
-					println!("{}{}", Utils::ansi_escape_text("91", "ERROR", INFO_PREFIX_LENGTH, self.ansi_enabled), e);
-					break;
-				}
-			};
+			let position = self.program_pointer;
+			let result = self.evaluate_command(command);
+			let (_, line, column, file_path) = &parser.commands_info[position];
+			self.warnings_found.report(position, file_path, *line, *column);
+			if let Err(e) = result {
+				let code = match self.warnings_found.denied() {
+					Some(warning) => warning.name(),
+					None if self.exit_code == Some(crate::EXIT_IO_ERROR) => "io",
+					None => "runtime",
+				};
+				Diagnostic::error(code, e).at(file_path, *line, *column).emit();
+				self.exit_code.get_or_insert(crate::EXIT_RUNTIME_ERROR);
+				break;
+			}
+			steps += 1;
 		}
 		if self.flags.debug {
-			println!("\n{}----- END OF CODE EXECUTION -----", Utils::ansi_escape_text("94", "DEBUG", INFO_PREFIX_LENGTH, self.ansi_enabled));
+			Diagnostic::debug("\n----- END OF CODE EXECUTION -----").emit();
+		}
+		if self.flags.final_snapshot_path.is_some() {
+			self.snapshot().finish(&self.flags);
 		}
 		if self.flags.debug {
-			println!("{}Main memory:", Utils::ansi_escape_text("94", "DEBUG", INFO_PREFIX_LENGTH, self.ansi_enabled));
-			println!("{}{:?}", Utils::ansi_escape_text("94", "DEBUG", INFO_PREFIX_LENGTH, self.ansi_enabled), self.memory);
-			println!("{}Local memory:", Utils::ansi_escape_text("94", "DEBUG", INFO_PREFIX_LENGTH, self.ansi_enabled));
-			println!("{}{:?}", Utils::ansi_escape_text("94", "DEBUG", INFO_PREFIX_LENGTH, self.ansi_enabled), local_memory);
+			Diagnostic::debug("Main memory:").emit();
+			Diagnostic::debug(format!("{:?}", self.memory)).emit();
+			Diagnostic::debug("Local memory:").emit();
+			Diagnostic::debug(format!("{:?}", self.local_memory)).emit();
 		}
+		self.exit_code.unwrap_or(0)
 	}
 
-	pub fn evaluate_command(&mut self, command: &str, local_memory: &mut [Vec<f64>; 2], local_memory_pointers: &mut [usize; 2], active_local_memory: usize) -> Result<usize, String> {
+	pub fn evaluate_command(&mut self, command: &str) -> Result<(), String> {
 		let is_local = command.starts_with('\'');
 		let raw_command = command;
 		let command = if is_local { &command[1..] } else { command };
-		let [(main_memory, main_memory_pointers, mut main_active_memory), (local_memory, local_memory_pointers, local_active_memory)] = if is_local {
+		let [(main_memory, mut main_active_memory), (local_memory, local_active_memory)] = if is_local {
 			[
-				(local_memory, local_memory_pointers, active_local_memory),
-				(&mut self.memory, &mut self.memory_pointers, self.active_memory),
+				(&mut self.local_memory, self.active_local_memory),
+				(&mut self.memory, self.active_memory),
 			]
 		} else {
 			[
-				(&mut self.memory, &mut self.memory_pointers, self.active_memory),
-				(local_memory, local_memory_pointers, active_local_memory),
+				(&mut self.memory, self.active_memory),
+				(&mut self.local_memory, self.active_local_memory),
 			]
 		};
 		let split_command = command.split('|').collect::<Vec<&str>>();
 		let (command, repeat) = if split_command.len() == 3 {
 			let count_str = split_command[1];
 			let num = if count_str.is_empty() {
-				main_memory[main_active_memory][main_memory_pointers[main_active_memory]].floor() as i128
-			} else if let Ok(val) = count_str.parse::<i128>() {
-				val
+				main_memory[main_active_memory].get().floor() as i128
 			} else {
-				1
+				count_str.parse::<i128>().unwrap_or(1)
 			};
 			let new_command = split_command[2];
 			if num < 0 {
@@ -220,54 +269,41 @@ impl Runner {
 		} else {
 			(command, 1)
 		};
-		for _ in 0..repeat {
+		// The pointer is moved by all the steps at once, other commands are ran repeatedly
+		let (iterations, steps) = if command == ">" || command == "<" { (1, repeat) } else { (repeat, 1) };
+		for _ in 0..iterations {
 			match command {
-				"!" => main_memory[main_active_memory][main_memory_pointers[main_active_memory]] += 1.0,
-				"~" => main_memory[main_active_memory][main_memory_pointers[main_active_memory]] -= 1.0,
-				"+" => {
-					main_memory[main_active_memory][main_memory_pointers[main_active_memory]] +=
-						main_memory[(main_active_memory as isize - 1).abs() as usize][main_memory_pointers[(main_active_memory as isize - 1).abs() as usize]]
-				}
-				"-" => {
-					main_memory[main_active_memory][main_memory_pointers[main_active_memory]] -=
-						main_memory[(main_active_memory as isize - 1).abs() as usize][main_memory_pointers[(main_active_memory as isize - 1).abs() as usize]]
-				}
-				"*" => {
-					main_memory[main_active_memory][main_memory_pointers[main_active_memory]] *=
-						main_memory[(main_active_memory as isize - 1).abs() as usize][main_memory_pointers[(main_active_memory as isize - 1).abs() as usize]]
-				}
-				"/" => {
-					main_memory[main_active_memory][main_memory_pointers[main_active_memory]] /=
-						main_memory[(main_active_memory as isize - 1).abs() as usize][main_memory_pointers[(main_active_memory as isize - 1).abs() as usize]]
-				}
-				"`" => main_memory[main_active_memory][main_memory_pointers[main_active_memory]] = rand::thread_rng().gen(),
-				">" => {
-					main_memory_pointers[main_active_memory] += 1;
-					if main_memory_pointers[main_active_memory] >= main_memory[main_active_memory].len() {
-						main_memory[main_active_memory].push(0.0);
-					}
-				}
+				"!" => main_memory[main_active_memory].set(main_memory[main_active_memory].get() + 1.0),
+				"~" => main_memory[main_active_memory].set(main_memory[main_active_memory].get() - 1.0),
+				"+" => main_memory[main_active_memory].set(main_memory[main_active_memory].get() + main_memory[main_active_memory ^ 1].get()),
+				"-" => main_memory[main_active_memory].set(main_memory[main_active_memory].get() - main_memory[main_active_memory ^ 1].get()),
+				"*" => main_memory[main_active_memory].set(main_memory[main_active_memory].get() * main_memory[main_active_memory ^ 1].get()),
+				"/" => main_memory[main_active_memory].set(main_memory[main_active_memory].get() / main_memory[main_active_memory ^ 1].get()),
+				"`" => main_memory[main_active_memory].set(self.rng.gen()),
+				">" => main_memory[main_active_memory].move_right(steps)?,
 				"<" => {
-					if main_memory_pointers[main_active_memory] == 0 {
-						main_memory[main_active_memory].insert(0, 0.0);
-						if !self.flags.disabled_warnings.too_left_pointer {
-							println!("{}You moved to the -1 index in memory. This will not crash the program, but should generally be avoided (you can use the --disable-warnings flag to disable all warnings or --disable-too-left-pointer-warning to disable this particular warning)", Utils::ansi_escape_text("93", "WARNING", INFO_PREFIX_LENGTH, self.ansi_enabled));
-						}
-					} else {
-						main_memory_pointers[main_active_memory] -= 1;
+					let moved_too_left = main_memory[main_active_memory].move_left(steps)?;
+					if moved_too_left {
+						self.warnings_found.found(&self.flags.warnings, Warning::TooLeftPointer, String::from("You moved to the -1 index in memory. This will not crash the program, but should generally be avoided (you can use --allow too-left-pointer or #disable-warnings too-left-pointer to disable this warning)"))?;
 					}
 				}
-				"_" => main_memory[main_active_memory][main_memory_pointers[main_active_memory]] = main_memory[main_active_memory][main_memory_pointers[main_active_memory]].floor(),
-				"&" => main_memory[main_active_memory][main_memory_pointers[main_active_memory]] = main_memory[main_active_memory][main_memory_pointers[main_active_memory]].ceil(),
-				"^" => main_active_memory = (main_active_memory as isize - 1).abs() as usize,
+				"_" => main_memory[main_active_memory].set(main_memory[main_active_memory].get().floor()),
+				"&" => main_memory[main_active_memory].set(main_memory[main_active_memory].get().ceil()),
+				"^" => main_active_memory ^= 1,
 				"$." => {
 					if self.input_cache.is_none() {
-						self.input_cache = Some(Utils::get_input_line());
+						match Utils::get_input_line() {
+							Ok(line) => self.input_cache = Some(line),
+							Err(e) => {
+								self.exit_code = Some(crate::EXIT_IO_ERROR);
+								return Err(format!("Couldn't read the input: {}", e));
+							}
+						}
 					}
 					let input = &self.input_cache.clone().unwrap();
 					self.input_cache = None;
-					main_memory[main_active_memory][main_memory_pointers[main_active_memory]] = match input.parse::<f64>() {
-						Ok(val) => val,
+					match input.parse::<f64>() {
+						Ok(val) => main_memory[main_active_memory].set(val),
 						Err(e) => {
 							return Err(format!("Failed to convert {} from input to a number: {}", input, e));
 						}
@@ -275,35 +311,43 @@ impl Runner {
 				}
 				"$," => {
 					if self.input_cache.is_none() {
-						self.input_cache = Some(Utils::get_input_line());
+						match Utils::get_input_line() {
+							Ok(line) => self.input_cache = Some(line),
+							Err(e) => {
+								self.exit_code = Some(crate::EXIT_IO_ERROR);
+								return Err(format!("Couldn't read the input: {}", e));
+							}
+						}
 					}
 					let input = &self.input_cache.clone().unwrap();
 					let (char, remainder) = Utils::next_char(input);
 					self.input_cache = if !remainder.is_empty() { Some(remainder.to_string()) } else { None };
-					main_memory[main_active_memory][main_memory_pointers[main_active_memory]] = (char as u32) as f64;
+					main_memory[main_active_memory].set((char as u32) as f64);
 				}
 				"\\." => {
-					print!("{}", main_memory[main_active_memory][main_memory_pointers[main_active_memory]]);
+					print!("{}", main_memory[main_active_memory].get());
 					if let Err(e) = Utils::flush_console() {
-						println!("{}{}", Utils::ansi_escape_text("91", "ERROR", INFO_PREFIX_LENGTH, self.ansi_enabled), e);
+						self.exit_code = Some(crate::EXIT_IO_ERROR);
+						return Err(format!("Couldn't write the output: {}", e));
 					}
 				}
-				"\\," => match char::from_u32(main_memory[main_active_memory][main_memory_pointers[main_active_memory]].floor() as u32) {
+				"\\," => match char::from_u32(main_memory[main_active_memory].get().floor() as u32) {
 					Some(c) => {
 						print!("{}", c);
 						if let Err(e) = Utils::flush_console() {
-							println!("{}{}", Utils::ansi_escape_text("91", "ERROR", INFO_PREFIX_LENGTH, self.ansi_enabled), e);
+							self.exit_code = Some(crate::EXIT_IO_ERROR);
+							return Err(format!("Couldn't write the output: {}", e));
 						}
 					}
 					None => {
 						return Err(format!(
 							"Failed to convert {} from memory to a character",
-							main_memory[main_active_memory][main_memory_pointers[main_active_memory]].floor()
+							main_memory[main_active_memory].get().floor()
 						));
 					}
 				},
 				"[" => {
-					if main_memory[main_active_memory][main_memory_pointers[main_active_memory]] == 0.0 {
+					if main_memory[main_active_memory].get() == 0.0 {
 						if let Some(index) = self.loops.iter().position(|value| *value == self.program_pointer) {
 							self.loops.remove(index);
 						}
@@ -313,7 +357,7 @@ impl Runner {
 					}
 				}
 				"]" | "@]" => {
-					if main_memory[main_active_memory][main_memory_pointers[main_active_memory]] == 0.0 {
+					if main_memory[main_active_memory].get() == 0.0 {
 						if let Some(index) = self.loops.iter().position(|value| *value == self.program_pointer) {
 							self.loops.remove(index);
 						}
@@ -322,7 +366,7 @@ impl Runner {
 					}
 				}
 				"[@" => {
-					if main_memory[main_active_memory][main_memory_pointers[main_active_memory]] == 0.0 && self.loops.contains(&self.program_pointer) {
+					if main_memory[main_active_memory].get() == 0.0 && self.loops.contains(&self.program_pointer) {
 						if let Some(index) = self.loops.iter().position(|value| *value == self.program_pointer) {
 							self.loops.remove(index);
 						}
@@ -332,74 +376,101 @@ impl Runner {
 					}
 				}
 				"?=" => {
-					let inactive_memory = (main_active_memory as isize - 1).abs() as usize;
-					if main_memory[main_active_memory][main_memory_pointers[main_active_memory]] == main_memory[inactive_memory][main_memory_pointers[inactive_memory]] {
+					let inactive_memory = main_active_memory ^ 1;
+					if main_memory[main_active_memory].get() == main_memory[inactive_memory].get() {
 						if let Some(current_loop) = self.loops.pop() {
 							self.program_pointer = *self.brackets.get(&current_loop).unwrap();
 						}
 					}
 				}
 				"?>" => {
-					let inactive_memory = (main_active_memory as isize - 1).abs() as usize;
-					if main_memory[main_active_memory][main_memory_pointers[main_active_memory]] > main_memory[inactive_memory][main_memory_pointers[inactive_memory]] {
+					let inactive_memory = main_active_memory ^ 1;
+					if main_memory[main_active_memory].get() > main_memory[inactive_memory].get() {
 						if let Some(current_loop) = self.loops.pop() {
 							self.program_pointer = *self.brackets.get(&current_loop).unwrap();
 						}
 					}
 				}
 				"?<" => {
-					let inactive_memory = (main_active_memory as isize - 1).abs() as usize;
-					if main_memory[main_active_memory][main_memory_pointers[main_active_memory]] < main_memory[inactive_memory][main_memory_pointers[inactive_memory]] {
+					let inactive_memory = main_active_memory ^ 1;
+					if main_memory[main_active_memory].get() < main_memory[inactive_memory].get() {
 						if let Some(current_loop) = self.loops.pop() {
 							self.program_pointer = *self.brackets.get(&current_loop).unwrap();
 						}
 					}
 				}
 				";" => {
-					std::mem::swap(
-						&mut local_memory[local_active_memory][local_memory_pointers[local_active_memory]],
-						&mut main_memory[main_active_memory][main_memory_pointers[main_active_memory]],
-					);
+					let (local_value, main_value) = (local_memory[local_active_memory].get(), main_memory[main_active_memory].get());
+					local_memory[local_active_memory].set(main_value);
+					main_memory[main_active_memory].set(local_value);
 				}
 				_ => {}
 			}
 		}
 		self.program_pointer += 1;
-		self.active_memory = if is_local { local_active_memory } else { main_active_memory };
+		if is_local {
+			self.active_local_memory = main_active_memory;
+		} else {
+			self.active_memory = main_active_memory;
+		}
 		if self.flags.debug_heavy {
-			println!(
-				"\n{}Raw command: {:?}",
-				Utils::ansi_escape_text("34", "HEAVY DEBUG", INFO_PREFIX_LENGTH, self.ansi_enabled),
-				raw_command
-			);
-			println!("{}Command executed: {:?}", Utils::ansi_escape_text("34", "HEAVY DEBUG", INFO_PREFIX_LENGTH, self.ansi_enabled), command);
-			println!(
-				"{}Command was executed on local memory: {:?}",
-				Utils::ansi_escape_text("34", "HEAVY DEBUG", INFO_PREFIX_LENGTH, self.ansi_enabled),
-				is_local
-			);
-			println!(
-				"{}Command repetitions: {:?}",
-				Utils::ansi_escape_text("34", "HEAVY DEBUG", INFO_PREFIX_LENGTH, self.ansi_enabled),
-				repeat
-			);
-			println!(
-				"{}Global memory: {:?}",
-				Utils::ansi_escape_text("34", "HEAVY DEBUG", INFO_PREFIX_LENGTH, self.ansi_enabled),
-				self.memory
-			);
-			println!(
-				"{}Global memory pointers: {:?}",
-				Utils::ansi_escape_text("34", "HEAVY DEBUG", INFO_PREFIX_LENGTH, self.ansi_enabled),
-				self.memory_pointers
-			);
-			println!(
-				"{}Active global memory: {:?}",
-				Utils::ansi_escape_text("34", "HEAVY DEBUG", INFO_PREFIX_LENGTH, self.ansi_enabled),
-				self.active_memory
-			);
+			Diagnostic::trace(format!("\nRaw command: {:?}", raw_command)).emit();
+			Diagnostic::trace(format!("Command executed: {:?}", command)).emit();
+			Diagnostic::trace(format!("Command was executed on local memory: {:?}", is_local)).emit();
+			Diagnostic::trace(format!("Command repetitions: {:?}", repeat)).emit();
+			Diagnostic::trace(format!("Global memory: {:?}", self.memory)).emit();
+			Diagnostic::trace(format!("Global memory pointers: {:?}", [self.memory[0].index(), self.memory[1].index()])).emit();
+			Diagnostic::trace(format!("Active global memory: {:?}", self.active_memory)).emit();
 			std::thread::sleep(std::time::Duration::from_millis(500));
 		}
-		Ok(if is_local { main_active_memory } else { local_active_memory })
+		Ok(())
+	}
+
+	pub fn snapshot(&self) -> Snapshot {
+		Snapshot {
+			version: VERSION.to_string(),
+			code: self.raw_code.clone(),
+			code_path: self.code_path.clone(),
+
+			program_pointer: self.program_pointer,
+
+			on_local: false,
+			loops: self.loops.clone(),
+			memory: self.memory.clone(),
+			active_memory: self.active_memory,
+			local_memory: self.local_memory.clone(),
+			active_local_memory: self.active_local_memory,
+			call_stack: vec![],
+
+			input_cache: self.input_cache.clone(),
+		}
+	}
+
+	pub fn restore(&mut self, snapshot: Snapshot) -> Result<(), String> {
+		snapshot.validate(VERSION)?;
+		self.program_pointer = snapshot.program_pointer;
+
+		self.loops = snapshot.loops;
+		self.memory = snapshot.memory;
+		self.active_memory = snapshot.active_memory;
+		self.local_memory = snapshot.local_memory;
+		self.active_local_memory = snapshot.active_local_memory;
+
+		self.input_cache = snapshot.input_cache;
+		Ok(())
+	}
+}
+
+impl super::Runner for Runner {
+	fn run(&mut self) -> i32 {
+		Runner::run(self)
+	}
+
+	fn restore(&mut self, snapshot: Snapshot) -> Result<(), String> {
+		Runner::restore(self, snapshot)
+	}
+
+	fn snapshot(&self) -> Snapshot {
+		Runner::snapshot(self)
 	}
 }
@@ -0,0 +1,757 @@
+use std::collections::HashMap;
+
+use crate::interpreter::memory::{CallFrame, MemoryRow};
+use crate::interpreter::snapshot::Snapshot;
+use crate::interpreter::source_map::SourceMap;
+use crate::Flags;
+use rand::rngs::StdRng;
+use rand::Rng;
+use regex::Regex;
+
+use super::frontend::{BracketsMatcher, Grammar, Lexer, Parser, Validator};
+use crate::{Diagnostic, FoundWarnings, Utils, Warning};
+
+pub const VERSION: &str = "0.5.0";
+
+pub fn grammar() -> Grammar {
+	Grammar {
+		rules: vec![
+			Regex::new(r"^(\|-?[0-9]*\|)*!").unwrap(),      // increment
+			Regex::new(r"^(\|-?[0-9]*\|)*~").unwrap(),      // decrement
+			Regex::new(r"^(\|-?[0-9]*\|)*\+").unwrap(),     // add
+			Regex::new(r"^(\|-?[0-9]*\|)*-").unwrap(),      // subtract
+			Regex::new(r"^(\|-?[0-9]*\|)*\*").unwrap(),     // multiply
+			Regex::new(r"^(\|-?[0-9]*\|)*/").unwrap(),      // divide
+			Regex::new(r"^`").unwrap(),                     // generate a random number from 0 (inclusive) to 1 (exclusive)
+			Regex::new(r"^(\|-?[0-9]*\|)*>").unwrap(),      // move right
+			Regex::new(r"^(\|-?[0-9]*\|)*<").unwrap(),      // move left
+			Regex::new(r"^_").unwrap(),                     // floor
+			Regex::new(r"^&").unwrap(),                     // ceil
+			Regex::new(r"^'").unwrap(),                     // switch between local and global memory
+			Regex::new(r"^\^").unwrap(),                    // switch active memory
+			Regex::new(r"^\[@?").unwrap(),                  // (do-)while start
+			Regex::new(r"^@?\]").unwrap(),                  // (do-)while end
+			Regex::new(r"^\$,").unwrap(),                   // input number
+			Regex::new(r"^,").unwrap(),                     // input character
+			Regex::new(r"^(\|-?[0-9]*\|)*\$\.").unwrap(),   // output number
+			Regex::new(r"^(\|-?[0-9]*\|)*\.").unwrap(),     // output character
+			Regex::new(r"^(\|-?[0-9]*\|)*\?=").unwrap(),    // break if active memory address is equal to inactive memory address
+			Regex::new(r"^(\|-?[0-9]*\|)*\?>").unwrap(),    // break if active memory address is greater than inactive memory address
+			Regex::new(r"^(\|-?[0-9]*\|)*\?<").unwrap(),    // break if active memory address is less than inactive memory address
+			Regex::new(r"^\?\?").unwrap(),                  // set current active memory address to its index
+			Regex::new(r"^;").unwrap(),                     // swap main and local memory addresses
+			Regex::new(r"^\|-?[0-9]+\|\{").unwrap(),        // function definition start
+			Regex::new(r"^\}").unwrap(),                    // function definition end
+			Regex::new(r"^\|-?[0-9]*\|%").unwrap(),         // function call
+			Regex::new(r"^(:|:?\r?\n)").unwrap(),           // end of line
+			Regex::new("^\"[^\"]*\"").unwrap(),             // comments
+			Regex::new(r"^[ \t\f\v]").unwrap(),             // whitespace
+			Regex::new(crate::PREPROCESSOR_REGEX).unwrap(), //preprocessor regex
+		],
+		line_end_required: false,
+	}
+}
+
+pub struct Info;
+
+impl super::Version for Info {
+	fn version(&self) -> &'static str {
+		VERSION
+	}
+
+	fn name(&self) -> &'static str {
+		"Functions"
+	}
+
+	fn changelog(&self) -> &'static [&'static str] {
+		&[
+			"Functions defined with |n|{ and } and called with |n|%",
+			"#assert statements checked with the --assertions flag",
+		]
+	}
+
+	fn grammar(&self) -> Grammar {
+		grammar()
+	}
+
+	fn runner(&self, code: String, source_map: SourceMap, flags: Flags) -> Box<dyn super::Runner> {
+		Box::new(Runner::new(code, self.grammar(), source_map, flags))
+	}
+
+	fn check(&self, parser: &Parser) -> Vec<Diagnostic> {
+		match Runner::find_functions(&parser.commands) {
+			Ok(_) => vec![],
+			Err((e, i)) => {
+				let (_, line, column, file_path) = &parser.commands_info[i];
+				vec![Diagnostic::error("function", format!("{} {}", e, parser.location(i))).at(file_path, *line, *column)]
+			}
+		}
+	}
+}
+
+// The start of each function by its number, and the end of each function by its start
+type Functions = (HashMap<i128, usize>, HashMap<usize, usize>);
+
+pub struct Runner {
+	flags: Flags,
+
+	brackets_matcher: BracketsMatcher,
+	brackets_categorised: HashMap<String, HashMap<usize, usize>>,
+	opposite_commands: HashMap<String, String>,
+
+	brackets: HashMap<usize, usize>,
+	functions: HashMap<i128, usize>,
+	function_ends: HashMap<usize, usize>,
+	raw_code: String,
+	grammar: Grammar,
+	code_path: std::path::PathBuf,
+	source_map: SourceMap,
+
+	program_pointer: usize,
+
+	on_local: bool,
+	loops: Vec<usize>,
+	memory: [MemoryRow; 2],
+	active_memory: usize,
+	local_memory: [MemoryRow; 2],
+	active_local_memory: usize,
+	call_stack: Vec<CallFrame>,
+
+	input_cache: Option<String>,
+	rng: StdRng,
+	exit_code: Option<i32>,
+
+	// Found by the last command, the run loop prints them with its location
+	warnings_found: FoundWarnings,
+}
+
+impl Runner {
+	pub fn new(raw_code: String, grammar: Grammar, source_map: SourceMap, flags: Flags) -> Self {
+		let seed = flags.seed;
+		let memory = Self::initial_memory(&flags);
+		Self {
+			flags,
+
+			brackets_matcher: BracketsMatcher::new(),
+
+			brackets: HashMap::new(),
+			functions: HashMap::new(),
+			function_ends: HashMap::new(),
+			brackets_categorised: HashMap::new(),
+			opposite_commands: HashMap::from([
+				("!".to_string(), "~".to_string()),
+				("~".to_string(), "!".to_string()),
+				("+".to_string(), "-".to_string()),
+				("-".to_string(), "+".to_string()),
+				("*".to_string(), "/".to_string()),
+				("/".to_string(), "*".to_string()),
+				(">".to_string(), "<".to_string()),
+				("<".to_string(), ">".to_string()),
+			]),
+
+			raw_code,
+			grammar,
+			code_path: source_map.segments[0].file_path.clone(),
+			source_map,
+
+			program_pointer: 0,
+
+			on_local: false,
+			loops: vec![],
+			memory: memory.clone(),
+			active_memory: 0,
+			local_memory: memory,
+			active_local_memory: 0,
+			call_stack: vec![],
+
+			input_cache: None,
+			rng: Utils::rng(seed),
+			exit_code: None,
+			warnings_found: FoundWarnings::new(),
+		}
+	}
+
+	fn initial_memory(flags: &Flags) -> [MemoryRow; 2] {
+		let mut memory = [MemoryRow::new(), MemoryRow::new()];
+		if !flags.no_brainfuck {
+			memory[1].set(1.0);
+		}
+		memory
+	}
+
+	pub fn run(&mut self) -> i32 {
+		if self.flags.debug {
+			Diagnostic::debug(format!("Raw code: {}", self.raw_code)).emit();
+		}
+		let lexer = Lexer::new(self.raw_code.clone(), self.grammar.clone(), self.source_map.clone());
+		let validator_result = Validator::run(lexer.clone(), self.flags.debug_heavy);
+		if let Err(e) = validator_result {
+			e.emit();
+			return crate::EXIT_SYNTAX_ERROR;
+		}
+		if self.flags.debug {
+			Diagnostic::debug("Valid code!").emit();
+		}
+		let mut parser = Parser::new();
+		let parser_result = parser.run(lexer);
+		if let Err(e) = parser_result {
+			Diagnostic::error("syntax", e).emit();
+			return crate::EXIT_SYNTAX_ERROR;
+		}
+		if self.flags.debug {
+			Diagnostic::debug(format!("Parsed commands: {:?}", parser.commands)).emit();
+		}
+		self.brackets_matcher.match_brackets(&parser.commands);
+		self.brackets_categorised = self.brackets_matcher.brackets.clone();
+		if let Some(&i) = self.brackets_matcher.unmatched(&parser.commands).first() {
+			let (command, line, column, file_path) = &parser.commands_info[i];
+			Diagnostic::error("unmatched-bracket", format!("`{}` without a matching bracket {}", command, parser.location(i)))
+				.at(file_path, *line, *column)
+				.emit();
+			return crate::EXIT_SYNTAX_ERROR;
+		}
+		if self.flags.debug_heavy {
+			Diagnostic::trace(format!("Matched brackets: {:?}", self.brackets_matcher.brackets)).emit();
+		}
+		for loop_type in self.brackets_categorised.keys() {
+			let map = self.brackets_categorised.get(loop_type).unwrap();
+			for (key, value) in map.iter() {
+				self.brackets.insert(*key, *value);
+			}
+		}
+		if self.flags.debug_heavy {
+			Diagnostic::trace(format!("Matched brackets uncategorised: {:?}", self.brackets)).emit();
+		}
+		if let Err((e, i)) = self.match_functions(&parser.commands) {
+			let (_, line, column, file_path) = &parser.commands_info[i];
+			Diagnostic::error("function", format!("{} {}", e, parser.location(i))).at(file_path, *line, *column).emit();
+			return crate::EXIT_SYNTAX_ERROR;
+		}
+		if self.flags.debug_heavy {
+			Diagnostic::trace(format!("Matched functions: {:?}", self.functions)).emit();
+		}
+		if self.flags.debug {
+			Diagnostic::debug("----- START OF CODE EXECUTION -----").emit();
+		}
+		let program_length = parser.commands.len();
+		if let Err(e) = Snapshot::validate_positions(self.program_pointer, &self.loops, &self.call_stack, program_length) {
+			Diagnostic::error("snapshot", e).emit();
+			return crate::EXIT_IO_ERROR;
+		}
+		let mut steps: u64 = 0;
+		while self.program_pointer < program_length {
+			if Snapshot::requested() || self.flags.step_limit.is_some_and(|limit| steps >= limit) {
+				self.snapshot().stop(&self.flags);
+				self.exit_code = Some(crate::EXIT_STOPPED);
+				break;
+			}
+			let command = &parser.commands[self.program_pointer];
+			if self.flags.assertions && command.starts_with('#') {
+				if let Err(e) = self.evaluate_assertion(command) {
+					let (_, line, column, file_path) = &parser.commands_info[self.program_pointer];
+					Diagnostic::error("assertion", format!("{} at {}:{} in {:?}", e, line, column, file_path.file_name().unwrap_or_default()))
+						.at(file_path, *line, *column)
+						.emit();
+					self.exit_code = Some(crate::EXIT_RUNTIME_ERROR);
+					break;
+				}
+			}
+			let position = self.program_pointer;
+			let result = self.evaluate_command(command);
+			let (_, line, column, file_path) = &parser.commands_info[position];
+			self.warnings_found.report(position, file_path, *line, *column);
+			if let Err(e) = result {
+				let code = match self.warnings_found.denied() {
+					Some(warning) => warning.name(),
+					None if self.exit_code == Some(crate::EXIT_IO_ERROR) => "io",
+					None => "runtime",
+				};
+				Diagnostic::error(code, e).at(file_path, *line, *column).emit();
+				self.exit_code.get_or_insert(crate::EXIT_RUNTIME_ERROR);
+				break;
+			}
+			steps += 1;
+		}
+		if self.flags.debug {
+			Diagnostic::debug("\n----- END OF CODE EXECUTION -----").emit();
+		}
+		if self.flags.final_snapshot_path.is_some() {
+			self.snapshot().finish(&self.flags);
+		}
+		if self.flags.debug {
+			Diagnostic::debug("Main memory:").emit();
+			Diagnostic::debug(format!("{:?}", self.memory)).emit();
+			Diagnostic::debug("Local memory:").emit();
+			Diagnostic::debug(format!("{:?}", self.local_memory)).emit();
+		}
+		self.exit_code.unwrap_or(0)
+	}
+
+	fn match_functions(&mut self, commands: &[String]) -> Result<(), (String, usize)> {
+		(self.functions, self.function_ends) = Self::find_functions(commands)?;
+		Ok(())
+	}
+
+	// Returns an error with the position of the command causing it
+	fn find_functions(commands: &[String]) -> Result<Functions, (String, usize)> {
+		let mut functions = HashMap::new();
+		let mut function_ends = HashMap::new();
+		let mut starts = vec![];
+		for (i, command) in commands.iter().enumerate() {
+			if command.ends_with('{') {
+				let id = command.split('|').nth(1).unwrap().parse::<i128>().map_err(|e| (format!("Invalid function number in {}: {}", command, e), i))?;
+				if functions.insert(id, i).is_some() {
+					return Err((format!("Function {} is defined more than once", id), i));
+				}
+				starts.push(i);
+			} else if command == "}" {
+				match starts.pop() {
+					Some(start) => {
+						function_ends.insert(start, i);
+					}
+					None => return Err((String::from("Found the end of a function definition without its start"), i)),
+				}
+			}
+		}
+		if let Some(start) = starts.first() {
+			return Err((format!("{} function definition(s) without an end", starts.len()), *start));
+		}
+		Ok((functions, function_ends))
+	}
+
+	fn evaluate_function_command(&mut self, command: &str) -> Result<(), String> {
+		if command.ends_with('{') {
+			// Definitions are skipped, their bodies only run when the function is called
+			self.program_pointer = *self.function_ends.get(&self.program_pointer).unwrap();
+		} else if command == "}" {
+			if let Some(frame) = self.call_stack.pop() {
+				self.program_pointer = frame.return_address;
+				self.on_local = frame.on_local;
+				self.loops = frame.loops;
+				self.local_memory = frame.local_memory;
+				self.active_local_memory = frame.active_local_memory;
+			}
+		} else {
+			let id_str = command.split('|').nth(1).unwrap();
+			let id = if id_str.is_empty() {
+				let memory = if self.on_local { &self.local_memory[self.active_local_memory] } else { &self.memory[self.active_memory] };
+				memory.get().floor() as i128
+			} else {
+				id_str.parse::<i128>().map_err(|e| format!("Invalid function number in {}: {}", command, e))?
+			};
+			let start = match self.functions.get(&id) {
+				Some(start) => *start,
+				None => return Err(format!("Attempted to call function {}, which is not defined", id)),
+			};
+			// Every call gets its own local memory, the global memory is shared
+			let local_memory = Self::initial_memory(&self.flags);
+			self.call_stack.push(CallFrame {
+				return_address: self.program_pointer,
+				on_local: std::mem::replace(&mut self.on_local, false),
+				loops: std::mem::take(&mut self.loops),
+				local_memory: std::mem::replace(&mut self.local_memory, local_memory),
+				active_local_memory: std::mem::replace(&mut self.active_local_memory, 0),
+			});
+			self.program_pointer = start;
+		}
+		self.program_pointer += 1;
+		if self.flags.debug_heavy {
+			Diagnostic::trace(format!("\nFunction command executed: {:?}", command)).emit();
+			Diagnostic::trace(format!("Call stack depth: {:?}", self.call_stack.len())).emit();
+		}
+		Ok(())
+	}
+
+	// Checks an `#assert <operator> <number or "inactive">` statement against the current cell
+	fn evaluate_assertion(&self, statement: &str) -> Result<(), String> {
+		let args = statement.trim().trim_start_matches('#').trim_end_matches(['#', ':']).split_whitespace().collect::<Vec<&str>>();
+		if args.first().is_none_or(|name| name.to_lowercase() != "assert") {
+			return Ok(());
+		}
+		let (operator, expected) = match args[1..] {
+			[operator, expected] => (operator, expected),
+			[expected] => ("==", expected),
+			_ => return Err(format!("Invalid assertion `{}`, expected for example `#assert == 5` or `#assert < inactive`", statement.trim())),
+		};
+		let memory = if self.on_local { &self.local_memory } else { &self.memory };
+		let active_memory = if self.on_local { self.active_local_memory } else { self.active_memory };
+		let value = memory[active_memory].get();
+		let (expected_value, expected_name) = if expected.to_lowercase() == "inactive" {
+			let inactive_value = memory[active_memory ^ 1].get();
+			(inactive_value, format!("the inactive cell ({})", inactive_value))
+		} else {
+			match expected.parse::<f64>() {
+				Ok(expected_value) => (expected_value, expected_value.to_string()),
+				Err(_) => return Err(format!("Invalid value `{}` in the assertion, expected a number or `inactive`", expected)),
+			}
+		};
+		let holds = match operator {
+			"==" => value == expected_value,
+			"!=" => value != expected_value,
+			"<" => value < expected_value,
+			"<=" => value <= expected_value,
+			">" => value > expected_value,
+			">=" => value >= expected_value,
+			_ => return Err(format!("Invalid operator `{}` in the assertion, expected one of ==, !=, <, <=, >, >=", operator)),
+		};
+		if holds {
+			Ok(())
+		} else {
+			Err(format!("Assertion failed: the current cell is {}, but it should be {} {}", value, operator, expected_name))
+		}
+	}
+
+	pub fn evaluate_command(&mut self, command: &str) -> Result<(), String> {
+		if command.ends_with('{') || command.ends_with('}') || command.ends_with('%') {
+			return self.evaluate_function_command(command);
+		}
+		let is_local = self.on_local;
+		let raw_command = command;
+		let [(main_memory, mut main_active_memory), (local_memory, local_active_memory)] = if is_local {
+			[
+				(&mut self.local_memory, self.active_local_memory),
+				(&mut self.memory, self.active_memory),
+			]
+		} else {
+			[
+				(&mut self.memory, self.active_memory),
+				(&mut self.local_memory, self.active_local_memory),
+			]
+		};
+		let split_command = command.split('|').collect::<Vec<&str>>();
+		let (command, repeat) = if split_command.len() == 3 {
+			let count_str = split_command[1];
+			let num = if count_str.is_empty() {
+				main_memory[main_active_memory].get().floor() as i128
+			} else {
+				count_str.parse::<i128>().unwrap_or(1)
+			};
+			let new_command = split_command[2];
+			if num < 0 {
+				if let Some(opposite_command) = self.opposite_commands.get(new_command) {
+					(opposite_command.as_str(), -num)
+				} else {
+					(new_command, 0)
+				}
+			} else {
+				(new_command, num)
+			}
+		} else {
+			(command, 1)
+		};
+		// The pointer is moved by all the steps at once, other commands are ran repeatedly
+		let (iterations, steps) = if command == ">" || command == "<" { (1, repeat) } else { (repeat, 1) };
+		for _ in 0..iterations {
+			match command {
+				"!" => main_memory[main_active_memory].set(main_memory[main_active_memory].get() + 1.0),
+				"~" => main_memory[main_active_memory].set(main_memory[main_active_memory].get() - 1.0),
+				"+" => main_memory[main_active_memory].set(main_memory[main_active_memory].get() + main_memory[main_active_memory ^ 1].get()),
+				"-" => main_memory[main_active_memory].set(main_memory[main_active_memory].get() - main_memory[main_active_memory ^ 1].get()),
+				"*" => main_memory[main_active_memory].set(main_memory[main_active_memory].get() * main_memory[main_active_memory ^ 1].get()),
+				"/" => {
+					let divisor = main_memory[main_active_memory ^ 1].get();
+					let divident = main_memory[main_active_memory].get();
+					if divisor == 0.0 {
+						let mut i = 0;
+						if divident >= 0.0 {
+							i += 1;
+						}
+						if divident > 0.0 {
+							i += 1;
+						}
+						let val = self.flags.sebek[i];
+						if let Some(res) = val {
+							main_memory[main_active_memory].set(res);
+						} else {
+							return Err(format!(
+								"Mr. Sebek would support you. Attempted division by 0 for {}. You can set up custom values for division by 0 with the --sebek flag.",
+								divident
+							));
+						}
+					} else {
+						main_memory[main_active_memory].set(divident / divisor)
+					}
+				}
+				"'" => self.on_local = !self.on_local,
+				"`" => main_memory[main_active_memory].set(self.rng.gen()),
+				">" => main_memory[main_active_memory].move_right(steps)?,
+				"<" => {
+					let moved_too_left = main_memory[main_active_memory].move_left(steps)?;
+					if moved_too_left {
+						self.warnings_found.found(&self.flags.warnings, Warning::TooLeftPointer, String::from("You moved to the -1 index in memory. This will not crash the program, but should generally be avoided (you can use --allow too-left-pointer or #disable-warnings too-left-pointer to disable this warning)"))?;
+					}
+				}
+				"_" => main_memory[main_active_memory].set(main_memory[main_active_memory].get().floor()),
+				"&" => main_memory[main_active_memory].set(main_memory[main_active_memory].get().ceil()),
+				"^" => main_active_memory ^= 1,
+				"$," => {
+					if self.input_cache.is_none() {
+						match Utils::get_input_line() {
+							Ok(line) => self.input_cache = Some(line),
+							Err(e) => {
+								self.exit_code = Some(crate::EXIT_IO_ERROR);
+								return Err(format!("Couldn't read the input: {}", e));
+							}
+						}
+					}
+					let input = &self.input_cache.clone().unwrap();
+					self.input_cache = None;
+					match input.parse::<f64>() {
+						Ok(val) => main_memory[main_active_memory].set(val),
+						Err(e) => {
+							return Err(format!("Failed to convert {} from input to a number: {}", input, e));
+						}
+					}
+				}
+				"," => {
+					if self.input_cache.is_none() {
+						match Utils::get_input_line() {
+							Ok(line) => self.input_cache = Some(line),
+							Err(e) => {
+								self.exit_code = Some(crate::EXIT_IO_ERROR);
+								return Err(format!("Couldn't read the input: {}", e));
+							}
+						}
+					}
+					let input = &self.input_cache.clone().unwrap();
+					let (char, remainder) = Utils::next_char(input);
+					self.input_cache = if !remainder.is_empty() { Some(remainder.to_string()) } else { None };
+					main_memory[main_active_memory].set((char as u32) as f64);
+				}
+				"$." => {
+					print!("{}", main_memory[main_active_memory].get());
+					if let Err(e) = Utils::flush_console() {
+						self.exit_code = Some(crate::EXIT_IO_ERROR);
+						return Err(format!("Couldn't write the output: {}", e));
+					}
+				}
+				"." => match char::from_u32(main_memory[main_active_memory].get().floor() as u32) {
+					Some(c) => {
+						print!("{}", c);
+						if let Err(e) = Utils::flush_console() {
+							self.exit_code = Some(crate::EXIT_IO_ERROR);
+							return Err(format!("Couldn't write the output: {}", e));
+						}
+					}
+					None => {
+						return Err(format!(
+							"Failed to convert {} from memory to a character",
+							main_memory[main_active_memory].get().floor()
+						));
+					}
+				},
+				"[" => {
+					if main_memory[main_active_memory].get() == 0.0 {
+						if let Some(index) = self.loops.iter().position(|value| *value == self.program_pointer) {
+							self.loops.remove(index);
+						}
+						self.program_pointer = *self.brackets.get(&self.program_pointer).unwrap();
+					} else if !self.loops.contains(&self.program_pointer) {
+						self.loops.push(self.program_pointer);
+					}
+				}
+				"]" | "@]" => {
+					if main_memory[main_active_memory].get() == 0.0 {
+						if let Some(index) = self.loops.iter().position(|value| *value == self.program_pointer) {
+							self.loops.remove(index);
+						}
+					} else {
+						self.program_pointer = *self.brackets.get(&self.program_pointer).unwrap();
+					}
+				}
+				"[@" => {
+					if main_memory[main_active_memory].get() == 0.0 && self.loops.contains(&self.program_pointer) {
+						if let Some(index) = self.loops.iter().position(|value| *value == self.program_pointer) {
+							self.loops.remove(index);
+						}
+						self.program_pointer = *self.brackets.get(&self.program_pointer).unwrap();
+					} else if !self.loops.contains(&self.program_pointer) {
+						self.loops.push(self.program_pointer);
+					}
+				}
+				"?=" => {
+					let inactive_memory = main_active_memory ^ 1;
+					if main_memory[main_active_memory].get() == main_memory[inactive_memory].get() {
+						if let Some(current_loop) = self.loops.pop() {
+							self.program_pointer = *self.brackets.get(&current_loop).unwrap();
+						}
+					}
+				}
+				"?>" => {
+					let inactive_memory = main_active_memory ^ 1;
+					if main_memory[main_active_memory].get() > main_memory[inactive_memory].get() {
+						if let Some(current_loop) = self.loops.pop() {
+							self.program_pointer = *self.brackets.get(&current_loop).unwrap();
+						}
+					}
+				}
+				"?<" => {
+					let inactive_memory = main_active_memory ^ 1;
+					if main_memory[main_active_memory].get() < main_memory[inactive_memory].get() {
+						if let Some(current_loop) = self.loops.pop() {
+							self.program_pointer = *self.brackets.get(&current_loop).unwrap();
+						}
+					}
+				}
+				"??" => {
+					main_memory[main_active_memory].set(main_memory[main_active_memory].index() as f64);
+				}
+				";" => {
+					let (local_value, main_value) = (local_memory[local_active_memory].get(), main_memory[main_active_memory].get());
+					local_memory[local_active_memory].set(main_value);
+					main_memory[main_active_memory].set(local_value);
+				}
+				_ => {}
+			}
+		}
+		self.program_pointer += 1;
+		if is_local {
+			self.active_local_memory = main_active_memory;
+		} else {
+			self.active_memory = main_active_memory;
+		}
+		if self.flags.debug_heavy {
+			Diagnostic::trace(format!("\nRaw command: {:?}", raw_command)).emit();
+			Diagnostic::trace(format!("Command executed: {:?}", command)).emit();
+			Diagnostic::trace(format!("Command was executed on local memory: {:?}", is_local)).emit();
+			Diagnostic::trace(format!("Command repetitions: {:?}", repeat)).emit();
+			Diagnostic::trace(format!("Global memory: {:?}", self.memory)).emit();
+			Diagnostic::trace(format!("Global memory pointers: {:?}", [self.memory[0].index(), self.memory[1].index()])).emit();
+			Diagnostic::trace(format!("Active global memory: {:?}", self.active_memory)).emit();
+			std::thread::sleep(std::time::Duration::from_millis(500));
+		}
+		Ok(())
+	}
+
+	pub fn snapshot(&self) -> Snapshot {
+		Snapshot {
+			version: VERSION.to_string(),
+			code: self.raw_code.clone(),
+			code_path: self.code_path.clone(),
+
+			program_pointer: self.program_pointer,
+
+			on_local: self.on_local,
+			loops: self.loops.clone(),
+			memory: self.memory.clone(),
+			active_memory: self.active_memory,
+			local_memory: self.local_memory.clone(),
+			active_local_memory: self.active_local_memory,
+			call_stack: self.call_stack.clone(),
+
+			input_cache: self.input_cache.clone(),
+		}
+	}
+
+	pub fn restore(&mut self, snapshot: Snapshot) -> Result<(), String> {
+		snapshot.validate(VERSION)?;
+		self.program_pointer = snapshot.program_pointer;
+
+		self.on_local = snapshot.on_local;
+		self.loops = snapshot.loops;
+		self.memory = snapshot.memory;
+		self.active_memory = snapshot.active_memory;
+		self.local_memory = snapshot.local_memory;
+		self.active_local_memory = snapshot.active_local_memory;
+		self.call_stack = snapshot.call_stack;
+
+		self.input_cache = snapshot.input_cache;
+		Ok(())
+	}
+}
+
+impl super::Runner for Runner {
+	fn run(&mut self) -> i32 {
+		Runner::run(self)
+	}
+
+	fn restore(&mut self, snapshot: Snapshot) -> Result<(), String> {
+		Runner::restore(self, snapshot)
+	}
+
+	fn snapshot(&self) -> Snapshot {
+		Runner::snapshot(self)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{grammar, Runner};
+	use crate::interpreter::snapshot::Snapshot;
+	use crate::interpreter::source_map::SourceMap;
+	use crate::{Flags, Warning, WarningLevel};
+
+	fn run(code: &str) -> (i32, Snapshot) {
+		run_with(code, Flags::new())
+	}
+
+	fn run_with(code: &str, flags: Flags) -> (i32, Snapshot) {
+		let mut source_map = SourceMap::new();
+		source_map.push(0, std::path::PathBuf::from("maumivu.au"), 1, 1);
+		let mut runner = Runner::new(code.to_string(), grammar(), source_map, flags);
+		let exit_code = runner.run();
+		(exit_code, runner.snapshot())
+	}
+
+	#[test]
+	fn definitions_only_run_when_called() {
+		let (exit_code, snapshot) = run("|1|{|5|!}");
+		assert_eq!(exit_code, 0);
+		assert!(snapshot.memory[0].cells().is_empty());
+		let (_, snapshot) = run("|1|{|5|!}|1|%|1|%");
+		assert_eq!(snapshot.memory[0].cells(), vec![(0, 10.0)]);
+	}
+
+	#[test]
+	fn calls_get_their_own_local_memory() {
+		let (exit_code, snapshot) = run("|1|{'|3|!'!}'|2|!'|1|%");
+		assert_eq!(exit_code, 0);
+		// The local memory of the caller is back once the call returns, the global memory is shared
+		assert_eq!(snapshot.local_memory[0].cells(), vec![(0, 2.0)]);
+		assert_eq!(snapshot.memory[0].cells(), vec![(0, 1.0)]);
+		assert!(!snapshot.on_local);
+		assert!(snapshot.call_stack.is_empty());
+	}
+
+	#[test]
+	fn functions_can_call_each_other() {
+		let (_, snapshot) = run("|2|{!}|1|{|2|%|2|%}|1|%");
+		assert_eq!(snapshot.memory[0].cells(), vec![(0, 2.0)]);
+	}
+
+	#[test]
+	fn loops_of_the_caller_are_restored() {
+		let (exit_code, snapshot) = run("|1|{[~]}!>|3|!<[>~<|1|%]");
+		assert_eq!(exit_code, 0);
+		assert!(snapshot.call_stack.is_empty());
+		assert_eq!(snapshot.memory[0].cells(), vec![(1, 2.0)]);
+	}
+
+	#[test]
+	fn the_called_function_can_come_from_the_cell() {
+		let (_, snapshot) = run("|2|{>!<}|2|!||%");
+		assert_eq!(snapshot.memory[0].cells(), vec![(0, 2.0), (1, 1.0)]);
+	}
+
+	#[test]
+	fn undefined_functions_are_runtime_errors() {
+		let (exit_code, _) = run("|3|%");
+		assert_eq!(exit_code, crate::EXIT_RUNTIME_ERROR);
+	}
+
+	#[test]
+	fn warning_levels_are_used() {
+		let mut flags = Flags::new();
+		// A denied warning stops the code before the next command
+		for (level, exit_code, cells) in [
+			(WarningLevel::Allow, 0, vec![(-1, 1.0)]),
+			(WarningLevel::Warn, 0, vec![(-1, 1.0)]),
+			(WarningLevel::Deny, crate::EXIT_RUNTIME_ERROR, vec![]),
+		] {
+			flags.warnings.set(Warning::TooLeftPointer, level);
+			let (code, snapshot) = run_with("<!", flags.clone());
+			assert_eq!(code, exit_code);
+			assert_eq!(snapshot.memory[0].cells(), cells);
+		}
+	}
+}
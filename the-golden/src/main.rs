@@ -1,50 +1,126 @@
 use dotenv::dotenv;
-use std::env;
 
+#[path = "./comparer.rs"]
+mod comparer;
+use comparer::Comparer;
+#[path = "./diagnostic.rs"]
+mod diagnostic;
+pub use diagnostic::{Diagnostic, Severity};
 #[path = "./flags.rs"]
 mod flags;
-pub use flags::Flags;
+pub use flags::{ColorMode, Flags, NonFinitePolicy};
+#[path = "./repl.rs"]
+mod repl;
+use repl::Repl;
+#[path = "./tester.rs"]
+mod tester;
+use tester::Tester;
 #[path = "./interpreter/interpreter.rs"]
 mod interpreter;
-use interpreter::Interpreter;
+use interpreter::{Checker, Formatter, Interpreter, Migrator, Snapshot};
 #[path = "./utils.rs"]
 mod utils;
 pub use utils::Utils;
+#[path = "./warnings.rs"]
+mod warnings;
+pub use warnings::{FoundWarnings, Warning, WarningLevel, Warnings, WARNINGS};
 
 pub const INFO_PREFIX_LENGTH: usize = 12;
 pub const PREPROCESSOR_REGEX: &str = "#[^#\r\n]*(#|#?\r?\n)";
 
+// The exit codes of the process - the commands checking something (check, test, compare, migrate, fmt) exit with 1 when it fails
+pub const EXIT_USAGE_ERROR: i32 = 2;
+pub const EXIT_SYNTAX_ERROR: i32 = 3;
+pub const EXIT_RUNTIME_ERROR: i32 = 4;
+pub const EXIT_IO_ERROR: i32 = 5;
+// The step limit was reached or the run was interrupted before the code ended
+pub const EXIT_STOPPED: i32 = 6;
+
 fn main() {
 	dotenv().ok();
-	if env::var("RUST_LOG").is_err() {
-		env::set_var("RUST_LOG", "INFO");
-	}
-	tracing_subscriber::fmt::init();
-	let ansi_enabled = enable_ansi_support::enable_ansi_support().is_ok();
 	let args: Vec<String> = std::env::args().collect();
 
 	let mut flags_handler = Flags::new();
-	flags_handler.parse(&args);
+	let parse_result = flags_handler.parse(&args);
+	// The flags before a wrong one are already applied, so even the error can be JSON if --message-format json comes first
+	let level = if flags_handler.debug_heavy {
+		tracing::Level::TRACE
+	} else if flags_handler.debug {
+		tracing::Level::DEBUG
+	} else {
+		tracing::Level::INFO
+	};
+	// The messages go to stderr and the rest to stdout, so each of them is colored only if it is a terminal
+	let ansi_enabled = Utils::ansi_enabled(flags_handler.color, std::io::stdout());
+	Diagnostic::init(flags_handler.json_messages, level, Utils::ansi_enabled(flags_handler.color, std::io::stderr()));
+	if let Err(e) = parse_result {
+		Diagnostic::error("usage", e).emit();
+		std::process::exit(EXIT_USAGE_ERROR);
+	}
+	if flags_handler.help {
+		print!("{}", Flags::help(flags_handler.help_command.as_deref().or(flags_handler.action.as_deref().filter(|action| *action != "help"))));
+		return;
+	}
 
-	let mut _action = String::new();
+	let mut action = String::new();
 	let mut version = None;
 	let mut code = String::new();
 	let mut code_path = std::path::PathBuf::new();
 
 	let cloned_flags = flags_handler.clone();
 	if let Some(a) = cloned_flags.action {
-		_action = a;
+		action = a;
+	}
+	if action == "versions" {
+		Interpreter::print_versions(ansi_enabled);
+		return;
+	}
+	if action == "migrate" {
+		let migrated = migrate(flags_handler);
+		std::process::exit(if migrated { 0 } else { 1 });
+	}
+	if action == "fmt" {
+		let formatted = format(flags_handler);
+		std::process::exit(if formatted { 0 } else { 1 });
 	}
-	if let Some(path) = cloned_flags.code_path {
+	if action == "repl" {
+		std::process::exit(Repl::new(flags_handler).run());
+	}
+	if action == "check" {
+		let path = cloned_flags.code_path.unwrap_or_default();
+		let valid = Checker::new(flags_handler, ansi_enabled).run(&path);
+		std::process::exit(if valid { 0 } else { 1 });
+	}
+	if action == "compare" {
+		let code_path = cloned_flags.code_path.unwrap_or_default();
+		let same = Comparer::new(flags_handler, ansi_enabled).run(&code_path);
+		std::process::exit(if same { 0 } else { 1 });
+	}
+	if action == "test" {
+		let directory = cloned_flags.code_path.unwrap_or_default();
+		let passed = Tester::new(flags_handler, ansi_enabled).run(&directory);
+		std::process::exit(if passed { 0 } else { 1 });
+	}
+	let snapshot = if let Some(path) = &cloned_flags.resume {
+		match Snapshot::load(path) {
+			Ok(s) => Some(s),
+			Err(e) => {
+				Diagnostic::error("io", e).emit();
+				std::process::exit(EXIT_IO_ERROR);
+			}
+		}
+	} else {
+		None
+	};
+	if let Some(s) = &snapshot {
+		code = s.code.clone();
+		code_path = s.code_path.clone();
+	} else if let Some(path) = cloned_flags.code_path {
 		code = match std::fs::read_to_string(&path) {
 			Ok(c) => c,
 			Err(e) => {
-				println!(
-					"{}Couldn't open a maumivu.au file from the provided path: {}",
-					Utils::ansi_escape_text("91", "ERROR", INFO_PREFIX_LENGTH, ansi_enabled),
-					e
-				);
-				return;
+				Diagnostic::error("io", format!("Couldn't open {:?}: {}", path, e)).emit();
+				std::process::exit(EXIT_IO_ERROR);
 			}
 		};
 		code_path = path;
@@ -53,22 +129,120 @@ fn main() {
 		code_path.set_file_name("<console_input_main>");
 	}
 	if code.is_empty() {
-		println!(
-			"{}No code provided - either provide a path to the maumivu.au file, or use the '- <code>' flag to run code from the command line directly",
-			Utils::ansi_escape_text("91", "ERROR", INFO_PREFIX_LENGTH, ansi_enabled)
-		);
-		return;
+		Diagnostic::error(
+			"usage",
+			"No code provided - either provide a path to the maumivu.au file, or use the '- <code>' flag to run code from the command line directly",
+		)
+		.emit();
+		std::process::exit(EXIT_USAGE_ERROR);
 	}
 	if let Some(v) = cloned_flags.version {
 		version = Some(v);
 	}
-	if let Ok(val) = env::var("LOGS") {
-		if val.to_lowercase() == "off" && cfg!(target_os = "windows") {
+	if let Some(s) = &snapshot {
+		version = Some(s.version.clone());
+	}
+	if cloned_flags.snapshot_path.is_some() {
+		if let Err(e) = Snapshot::listen_for_signal() {
+			Diagnostic::warning("snapshot", e).emit();
+		}
+	}
+	#[cfg(target_os = "windows")]
+	if let Ok(val) = std::env::var("LOGS") {
+		if val.to_lowercase() == "off" {
 			winconsole::window::hide();
 		}
 	}
-	if cloned_flags.no_console && cfg!(target_os = "windows") {
+	#[cfg(target_os = "windows")]
+	if cloned_flags.no_console {
 		winconsole::window::hide();
 	}
-	Interpreter::new(version, code, code_path, flags_handler, ansi_enabled).run();
+	// The code can't be prepared for running if it can't be preprocessed or no version matches its requirement
+	let exit_code = match Interpreter::new(version, code, code_path, flags_handler, snapshot) {
+		Ok(interpreter) => interpreter.run(),
+		Err(e) => {
+			e.emit();
+			if e.code == Some("io") {
+				EXIT_IO_ERROR
+			} else {
+				EXIT_SYNTAX_ERROR
+			}
+		}
+	};
+	std::process::exit(exit_code);
+}
+
+// Returns true if the code was migrated
+fn migrate(flags: Flags) -> bool {
+	let code_path = match flags.code_path {
+		Some(path) => path,
+		None => {
+			Diagnostic::error("usage", "No file to migrate provided").emit();
+			return false;
+		}
+	};
+	let result = std::fs::read_to_string(&code_path).map_err(|e| format!("Couldn't open {:?}: {}", code_path, e)).and_then(|code| {
+		let migrator = Migrator::new(flags.migrate_from, flags.migrate_to, &code)?;
+		let migration = migrator.migrate(&code, &code_path)?;
+		// The original file is only replaced if it is given as the output, otherwise the code is printed
+		match &flags.output_path {
+			Some(output_path) => std::fs::write(output_path, &migration.code).map_err(|e| format!("Couldn't write {:?}: {}", output_path, e))?,
+			None => print!("{}", migration.code),
+		}
+		Ok((migrator, migration, flags.output_path))
+	});
+	match result {
+		Ok((migrator, migration, output_path)) => {
+			for problem in &migration.problems {
+				Diagnostic::warning(
+					"migration",
+					format!("{} at {}:{} in {:?}", problem.message, problem.line, problem.column, code_path.file_name().unwrap_or_default()),
+				)
+				.at(&code_path, problem.line, problem.column)
+				.emit();
+			}
+			let destination = match output_path {
+				Some(output_path) => format!("{:?}", output_path),
+				None => String::from("the standard output"),
+			};
+			Diagnostic::info(format!(
+				"Migrated {:?} from {} to {} into {} ({} construct(s) to check by hand)",
+				code_path,
+				migrator.from,
+				migrator.to,
+				destination,
+				migration.problems.len()
+			))
+			.emit();
+			true
+		}
+		Err(e) => {
+			Diagnostic::error("migration", e).emit();
+			false
+		}
+	}
+}
+
+// Returns true if the code was formatted
+fn format(flags: Flags) -> bool {
+	let Some(code_path) = flags.code_path else {
+		Diagnostic::error("usage", "No file to format provided").emit();
+		return false;
+	};
+	let result = std::fs::read_to_string(&code_path).map_err(|e| format!("Couldn't open {:?}: {}", code_path, e)).and_then(|code| {
+		let formatted = Formatter::new(flags.version, &code, &code_path)?.format(&code, &code_path)?;
+		// Like the migrated code, the original file is only replaced if it is given as the output
+		match &flags.output_path {
+			Some(output_path) => std::fs::write(output_path, &formatted).map_err(|e| format!("Couldn't write {:?}: {}", output_path, e)),
+			None => {
+				print!("{}", formatted);
+				Ok(())
+			}
+		}
+	});
+	if let Err(e) = result {
+		Diagnostic::error("fmt", e).emit();
+		return false;
+	}
+	true
 }
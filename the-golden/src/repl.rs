@@ -0,0 +1,80 @@
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+
+use crate::interpreter::{Interpreter, Snapshot};
+use crate::{Diagnostic, Flags};
+
+pub struct Repl {
+	flags: Flags,
+}
+
+impl Repl {
+	pub fn new(flags: Flags) -> Self {
+		Self { flags }
+	}
+
+	// Runs the lines as they are typed, each one continues from the memory the previous ones left, returns the exit code
+	pub fn run(&self) -> i32 {
+		let version = match Interpreter::resolve_version(self.flags.version.clone().unwrap_or_else(|| String::from("latest"))) {
+			Ok(version) => version,
+			Err(e) => {
+				Diagnostic::error("version", e).emit();
+				return crate::EXIT_USAGE_ERROR;
+			}
+		};
+		let interactive = std::io::stdin().is_terminal();
+		if interactive {
+			Diagnostic::info(format!(
+				"The Golden {} - each line runs once you type it and the memory stays for the next ones, end with Ctrl+D",
+				version
+			))
+			.emit();
+		}
+		// The lines that ran without an error, the new line is run after them so the functions and macros they define can be used
+		let mut code = String::new();
+		let mut state: Option<Snapshot> = None;
+		loop {
+			if interactive {
+				print!("> ");
+				std::io::stdout().flush().ok();
+			}
+			let mut line = String::new();
+			match std::io::stdin().lock().read_line(&mut line) {
+				Ok(0) => break,
+				Ok(_) => {}
+				Err(e) => {
+					Diagnostic::error("io", format!("Couldn't read the line: {}", e)).emit();
+					return crate::EXIT_IO_ERROR;
+				}
+			}
+			if line.trim().is_empty() {
+				continue;
+			}
+			if !line.ends_with('\n') {
+				line.push('\n');
+			}
+			let candidate = format!("{}{}", code, line);
+			// The problems of the earlier lines were already reported, so a line with a broken statement isn't kept
+			let flags = Flags {
+				strict_preprocessor: true,
+				..self.flags.clone()
+			};
+			let (exit_code, end_state) = match Interpreter::new(Some(version.clone()), candidate.clone(), PathBuf::from("<repl>"), flags, state.clone()) {
+				Ok(interpreter) => interpreter.run_keeping_state(),
+				Err(e) => {
+					e.emit();
+					continue;
+				}
+			};
+			// A line that failed is forgotten along with what it did to the memory
+			if exit_code == 0 {
+				code = candidate;
+				state = end_state;
+			}
+		}
+		if interactive {
+			println!();
+		}
+		0
+	}
+}
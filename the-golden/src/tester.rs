@@ -0,0 +1,417 @@
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+
+use crate::{Diagnostic, Flags, NonFinitePolicy, Utils, INFO_PREFIX_LENGTH};
+
+pub const DEFAULT_TIMEOUT: u64 = 30;
+
+// A program with a sibling .out file or expectations written in its code (and optionally an .in file)
+#[derive(Clone, Debug)]
+struct Test {
+	code_path: PathBuf,
+	input_path: PathBuf,
+	output_path: Option<PathBuf>,
+	expected_words: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug)]
+enum Outcome {
+	Passed,
+	Failed { expected: String, actual: String },
+	Crashed { reason: String, errors: String },
+	Broken(String),
+}
+
+// What a program printed and how it ended
+pub struct ProgramRun {
+	pub output: Vec<u8>,
+	pub errors: Vec<u8>,
+	// None if the program was killed by a signal
+	pub exit_code: Option<i32>,
+}
+
+pub struct Tester {
+	flags: Flags,
+	ansi_enabled: bool,
+}
+
+impl Tester {
+	pub fn new(flags: Flags, ansi_enabled: bool) -> Self {
+		Self { flags, ansi_enabled }
+	}
+
+	// Returns true if all the tests passed
+	pub fn run(&self, directory: &Path) -> bool {
+		let mut tests = vec![];
+		if let Err(e) = Self::find_tests(directory, &mut tests) {
+			Diagnostic::error("io", format!("Couldn't read the tests from {:?}: {}", directory, e)).emit();
+			return false;
+		}
+		tests.sort_by(|a, b| a.code_path.cmp(&b.code_path));
+		if tests.is_empty() {
+			Diagnostic::warning(
+				"test",
+				format!(
+					"No tests found in {:?} - a test is a .au file with a .out file of the same name next to it or with expectations in its code",
+					directory
+				),
+			)
+			.emit();
+			return true;
+		}
+
+		let executable = match std::env::current_exe() {
+			Ok(path) => path,
+			Err(e) => {
+				Diagnostic::error("io", format!("Couldn't find the interpreter executable: {}", e)).emit();
+				return false;
+			}
+		};
+		let arguments = Self::forwarded_arguments(&self.flags);
+		let timeout = Duration::from_secs(self.flags.timeout.unwrap_or(DEFAULT_TIMEOUT));
+		let outcomes = Mutex::new(vec![None; tests.len()]);
+		let next_test = AtomicUsize::new(0);
+		let threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1).min(tests.len());
+		std::thread::scope(|scope| {
+			for _ in 0..threads {
+				scope.spawn(|| loop {
+					let index = next_test.fetch_add(1, Ordering::SeqCst);
+					let Some(test) = tests.get(index) else {
+						break;
+					};
+					let outcome = Self::run_test(&executable, &arguments, timeout, test);
+					outcomes.lock().unwrap()[index] = Some(outcome);
+				});
+			}
+		});
+
+		let mut failed = 0;
+		for (test, outcome) in tests.iter().zip(outcomes.into_inner().unwrap()) {
+			let name = test.code_path.strip_prefix(directory).unwrap_or(&test.code_path).display();
+			match outcome.unwrap_or_else(|| Outcome::Broken(String::from("The test was not run"))) {
+				Outcome::Passed => println!("{}{}", Utils::ansi_escape_text("92", "PASS", INFO_PREFIX_LENGTH, self.ansi_enabled), name),
+				Outcome::Failed { expected, actual } => {
+					failed += 1;
+					println!("{}{}", Utils::ansi_escape_text("91", "FAIL", INFO_PREFIX_LENGTH, self.ansi_enabled), name);
+					for line in Self::diff(&expected, &actual) {
+						println!("{}{}", " ".repeat(INFO_PREFIX_LENGTH), line);
+					}
+				}
+				Outcome::Crashed { reason, errors } => {
+					failed += 1;
+					println!("{}{}: {}", Utils::ansi_escape_text("91", "FAIL", INFO_PREFIX_LENGTH, self.ansi_enabled), name, reason);
+					for line in errors.lines() {
+						println!("{}{}", " ".repeat(INFO_PREFIX_LENGTH), line);
+					}
+				}
+				Outcome::Broken(reason) => {
+					failed += 1;
+					println!("{}{}: {}", Utils::ansi_escape_text("91", "FAIL", INFO_PREFIX_LENGTH, self.ansi_enabled), name, reason);
+				}
+			}
+		}
+		println!("{} passed, {} failed", tests.len() - failed, failed);
+		failed == 0
+	}
+
+	fn find_tests(directory: &Path, tests: &mut Vec<Test>) -> std::io::Result<()> {
+		for entry in std::fs::read_dir(directory)? {
+			let path = entry?.path();
+			if path.is_dir() {
+				Self::find_tests(&path, tests)?;
+			} else if path.extension().is_some_and(|extension| extension == "au") {
+				let output_path = Some(path.with_extension("out")).filter(|output_path| output_path.is_file());
+				let expected_words = Self::find_expectations(&std::fs::read_to_string(&path)?);
+				if output_path.is_some() || expected_words.is_some() {
+					tests.push(Test {
+						input_path: path.with_extension("in"),
+						output_path,
+						expected_words,
+						code_path: path,
+					});
+				}
+			}
+		}
+		Ok(())
+	}
+
+	// The words from the `"expect: ..."` comments and `#expect ...` statements in the code, in order
+	fn find_expectations(code: &str) -> Option<Vec<String>> {
+		let comment_rule = Regex::new("^\"\\s*expect:([^\"]*)\"").unwrap();
+		let statement_rule = Regex::new(crate::PREPROCESSOR_REGEX).unwrap();
+		let mut expectations = vec![];
+		let mut in_comment = false;
+		for (index, character) in code.char_indices() {
+			if character == '"' {
+				if !in_comment {
+					if let Some(captures) = comment_rule.captures(&code[index..]) {
+						expectations.push(captures[1].to_string());
+					}
+				}
+				in_comment = !in_comment;
+			} else if character == '#' && !in_comment {
+				if let Some(statement) = statement_rule.find(&code[index..]).filter(|statement| statement.start() == 0) {
+					let statement = statement.as_str().trim().trim_start_matches('#').trim_end_matches(['#', ':']);
+					if let Some((name, text)) = statement.split_once(' ') {
+						if name.to_lowercase() == "expect" {
+							expectations.push(text.to_string());
+						}
+					}
+				}
+			}
+		}
+		if expectations.is_empty() {
+			return None;
+		}
+		Some(expectations.iter().flat_map(|text| text.split_whitespace().map(String::from)).collect())
+	}
+
+	// The flags that should also apply to the tested programs
+	pub fn forwarded_arguments(flags: &Flags) -> Vec<String> {
+		let mut arguments = vec![];
+		if let Some(version) = &flags.version {
+			arguments.extend([String::from("--version"), version.clone()]);
+		}
+		if flags.sebek.iter().any(|value| value.is_some()) {
+			let sebek = flags.sebek.iter().flatten().map(|value| value.to_string()).collect::<Vec<String>>();
+			arguments.extend([String::from("--sebek"), sebek.join("|")]);
+		}
+		if let Some(seed) = flags.seed {
+			arguments.extend([String::from("--seed"), seed.to_string()]);
+		}
+		if let Some(step_limit) = flags.step_limit {
+			arguments.extend([String::from("--step-limit"), step_limit.to_string()]);
+		}
+		for (name, value) in &flags.defines {
+			arguments.extend([String::from("--define"), format!("{}={}", name, value)]);
+		}
+		if let Some(policy) = flags.non_finite {
+			let policy = match policy {
+				NonFinitePolicy::Error => String::from("error"),
+				NonFinitePolicy::Saturate => String::from("saturate"),
+				NonFinitePolicy::Replace(number) => number.to_string(),
+			};
+			arguments.extend([String::from("--non-finite"), policy]);
+		}
+		if flags.no_brainfuck {
+			arguments.push(String::from("--no-brainfuck"));
+		}
+		arguments.extend(flags.warnings.arguments());
+		if flags.assertions {
+			arguments.push(String::from("--assertions"));
+		}
+		if flags.strict_preprocessor {
+			arguments.push(String::from("--strict-preprocessor"));
+		}
+		arguments
+	}
+
+	fn run_test(executable: &Path, arguments: &[String], timeout: Duration, test: &Test) -> Outcome {
+		let expected = match &test.output_path {
+			Some(output_path) => match std::fs::read_to_string(output_path) {
+				Ok(expected) => Some(expected),
+				Err(e) => return Outcome::Broken(format!("Couldn't read {:?}: {}", output_path, e)),
+			},
+			None => None,
+		};
+		let code_path = test.code_path.canonicalize().unwrap_or_else(|_| test.code_path.clone());
+		let mut program_arguments = vec![String::from("run"), code_path.to_string_lossy().to_string()];
+		program_arguments.extend_from_slice(arguments);
+		// Given as a flag, so the .in file wins over the input of the golden.toml file
+		if test.input_path.is_file() {
+			let input_path = test.input_path.canonicalize().unwrap_or_else(|_| test.input_path.clone());
+			program_arguments.extend([String::from("--input"), input_path.to_string_lossy().to_string()]);
+		}
+		match Self::run_program(executable, &program_arguments, vec![], timeout) {
+			Ok(run) => Self::check(expected, test.expected_words.as_deref(), &run),
+			Err(e) => Outcome::Broken(e),
+		}
+	}
+
+	// Compares what the program printed to the .out file and the expectations in its code
+	fn check(expected: Option<String>, expected_words: Option<&[String]>, run: &ProgramRun) -> Outcome {
+		// Programs that never end are stopped by the step limit, but a program failing after printing the right output still fails the test
+		let stopped = run.exit_code == Some(crate::EXIT_STOPPED);
+		if !stopped && run.exit_code != Some(0) {
+			let reason = match run.exit_code {
+				Some(code) => format!("Exited with the code {}", code),
+				None => String::from("Killed by a signal"),
+			};
+			return Outcome::Crashed {
+				reason,
+				errors: String::from_utf8_lossy(&run.errors).to_string(),
+			};
+		}
+		let actual = String::from_utf8_lossy(&run.output).to_string();
+
+		if let Some(expected) = expected {
+			if Self::normalize(&actual) != Self::normalize(&expected) {
+				return Outcome::Failed { expected, actual };
+			}
+		}
+		if let Some(expected_words) = expected_words {
+			let mut actual_words = actual.split_whitespace().collect::<Vec<&str>>();
+			// Only the beginning of the output of a stopped program is expected, the last word may not even be complete
+			if stopped {
+				actual_words.truncate(expected_words.len());
+			}
+			if actual_words != expected_words {
+				return Outcome::Failed {
+					expected: expected_words.join("\n"),
+					actual: actual_words.join("\n"),
+				};
+			}
+		}
+		Outcome::Passed
+	}
+
+	// Runs the interpreter with the input until it ends
+	pub fn run_program(executable: &Path, arguments: &[String], input: Vec<u8>, timeout: Duration) -> Result<ProgramRun, String> {
+		let mut child = Command::new(executable)
+			.args(arguments)
+			.stdin(Stdio::piped())
+			.stdout(Stdio::piped())
+			.stderr(Stdio::piped())
+			.spawn()
+			.map_err(|e| format!("Couldn't start the interpreter: {}", e))?;
+
+		// All the pipes are handled on their own threads so a program can't block on a full pipe
+		let mut stdin = child.stdin.take().unwrap();
+		let writer = std::thread::spawn(move || stdin.write_all(&input));
+		let output = Arc::new(Mutex::new(vec![]));
+		let reader = Self::read_pipe(child.stdout.take().unwrap(), Arc::clone(&output));
+		let errors = Arc::new(Mutex::new(vec![]));
+		let errors_reader = Self::read_pipe(child.stderr.take().unwrap(), Arc::clone(&errors));
+		let started = Instant::now();
+		let exit_code;
+		loop {
+			match child.try_wait() {
+				Ok(Some(status)) => {
+					exit_code = status.code();
+					break;
+				}
+				Ok(None) if started.elapsed() > timeout => {
+					child.kill().ok();
+					child.wait().ok();
+					return Err(format!(
+						"Timed out after {} seconds (use the --step-limit flag to stop endless programs or --timeout to wait longer)",
+						timeout.as_secs()
+					));
+				}
+				Ok(None) => std::thread::sleep(Duration::from_millis(10)),
+				Err(e) => return Err(format!("Couldn't wait for the interpreter: {}", e)),
+			}
+		}
+		writer.join().ok();
+		if reader.join().is_err() || errors_reader.join().is_err() {
+			return Err(String::from("Couldn't read the output of the program"));
+		}
+		let output = output.lock().unwrap().clone();
+		let errors = errors.lock().unwrap().clone();
+		Ok(ProgramRun { output, errors, exit_code })
+	}
+
+	fn read_pipe(mut pipe: impl Read + Send + 'static, into: Arc<Mutex<Vec<u8>>>) -> std::thread::JoinHandle<()> {
+		std::thread::spawn(move || {
+			let mut buffer = [0; 1024];
+			while let Ok(read @ 1..) = pipe.read(&mut buffer) {
+				into.lock().unwrap().extend_from_slice(&buffer[..read]);
+			}
+		})
+	}
+
+	// Line endings and trailing new lines don't matter
+	fn normalize(text: &str) -> String {
+		text.replace("\r\n", "\n").trim_end_matches('\n').to_string()
+	}
+
+	// A line diff of the two texts, based on their longest common subsequence of lines
+	fn diff(expected: &str, actual: &str) -> Vec<String> {
+		let (expected, actual) = (Self::normalize(expected), Self::normalize(actual));
+		let expected = expected.split('\n').collect::<Vec<&str>>();
+		let actual = actual.split('\n').collect::<Vec<&str>>();
+		let mut common = vec![vec![0; actual.len() + 1]; expected.len() + 1];
+		for i in (0..expected.len()).rev() {
+			for j in (0..actual.len()).rev() {
+				common[i][j] = if expected[i] == actual[j] { common[i + 1][j + 1] + 1 } else { common[i + 1][j].max(common[i][j + 1]) };
+			}
+		}
+		let mut lines = vec![];
+		let (mut i, mut j) = (0, 0);
+		while i < expected.len() || j < actual.len() {
+			if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+				lines.push(format!("  {}", expected[i]));
+				i += 1;
+				j += 1;
+			} else if i < expected.len() && (j == actual.len() || common[i + 1][j] >= common[i][j + 1]) {
+				lines.push(format!("- {}", expected[i]));
+				i += 1;
+			} else {
+				lines.push(format!("+ {}", actual[j]));
+				j += 1;
+			}
+		}
+		lines
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Outcome, ProgramRun, Tester};
+
+	fn run(output: &str, exit_code: Option<i32>) -> ProgramRun {
+		ProgramRun {
+			output: output.as_bytes().to_vec(),
+			errors: vec![],
+			exit_code,
+		}
+	}
+
+	fn words(text: &str) -> Vec<String> {
+		text.split_whitespace().map(String::from).collect()
+	}
+
+	#[test]
+	fn expectations_are_collected_in_order() {
+		let code = "\"expect: 1 1\"!$.\n#expect 2 3\n\"a comment #expect 4\"\"not expect: 5\" \"  expect:8\"";
+		assert_eq!(Tester::find_expectations(code), Some(words("1 1 2 3 8")));
+		assert_eq!(Tester::find_expectations("!$. \"prints 1\"\n#version 0.6.0\n"), None);
+	}
+
+	#[test]
+	fn out_files_ignore_line_endings() {
+		let expected = Some(String::from("Hello\nworld\n"));
+		assert!(matches!(Tester::check(expected.clone(), None, &run("Hello\r\nworld", Some(0))), Outcome::Passed));
+		assert!(matches!(Tester::check(expected.clone(), None, &run("Hello\nworld\n\n", Some(0))), Outcome::Passed));
+		assert!(matches!(Tester::check(expected, None, &run("Hello world\n", Some(0))), Outcome::Failed { .. }));
+		assert_eq!(Tester::diff("a\nb\nc", "a\nc\nd\n"), ["  a", "- b", "  c", "+ d"]);
+	}
+
+	#[test]
+	fn expected_words_ignore_spacing() {
+		let expected = words("1 1 2");
+		assert!(matches!(Tester::check(None, Some(&expected), &run("1\n1 2\n", Some(0))), Outcome::Passed));
+		assert!(matches!(Tester::check(None, Some(&expected), &run("1 1 2 3", Some(0))), Outcome::Failed { .. }));
+		assert!(matches!(Tester::check(None, Some(&expected), &run("1 1", Some(0))), Outcome::Failed { .. }));
+	}
+
+	#[test]
+	fn stopped_programs_only_need_to_start_with_the_expected_words() {
+		let expected = words("1 1 2");
+		assert!(matches!(Tester::check(None, Some(&expected), &run("1 1 2 3 5 8 1", Some(crate::EXIT_STOPPED))), Outcome::Passed));
+		assert!(matches!(Tester::check(None, Some(&expected), &run("1 1", Some(crate::EXIT_STOPPED))), Outcome::Failed { .. }));
+	}
+
+	#[test]
+	fn failing_programs_fail_even_with_the_right_output() {
+		let expected = words("1 1 2");
+		assert!(matches!(Tester::check(None, Some(&expected), &run("1 1 2", Some(crate::EXIT_RUNTIME_ERROR))), Outcome::Crashed { .. }));
+		assert!(matches!(Tester::check(Some(String::from("1 1 2")), None, &run("1 1 2", None)), Outcome::Crashed { .. }));
+	}
+}
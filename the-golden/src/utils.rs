@@ -1,4 +1,16 @@
-use std::io::Write;
+use std::io::{BufRead, BufReader, IsTerminal, Write};
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::{ColorMode, NonFinitePolicy};
+
+lazy_static! {
+	// The input file of the code, if it doesn't read the piped input
+	static ref INPUT_FILE: Mutex<Option<BufReader<std::fs::File>>> = Mutex::new(None);
+}
 
 pub struct Utils {}
 
@@ -14,10 +26,41 @@ impl Utils {
 		res
 	}
 
-	pub fn get_input_line() -> String {
+	// Whether to color what is printed to the stream - NO_COLOR and redirecting it into a file or a pipe turn the colors off, unless they are forced with --color always
+	pub fn ansi_enabled(mode: ColorMode, stream: impl IsTerminal) -> bool {
+		match mode {
+			ColorMode::Never => false,
+			ColorMode::Always => {
+				enable_ansi_support::enable_ansi_support().ok();
+				true
+			}
+			ColorMode::Auto => {
+				let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+				!no_color && stream.is_terminal() && enable_ansi_support::enable_ansi_support().is_ok()
+			}
+		}
+	}
+
+	// The random number generator of the runners - seeded ones always generate the same numbers
+	pub fn rng(seed: Option<u64>) -> StdRng {
+		match seed {
+			Some(seed) => StdRng::seed_from_u64(seed),
+			None => StdRng::from_entropy(),
+		}
+	}
+
+	pub fn get_input_line() -> std::io::Result<String> {
 		let mut input = String::new();
-		std::io::stdin().read_line(&mut input).unwrap();
-		input.trim().to_string()
+		match INPUT_FILE.lock().unwrap().as_mut() {
+			Some(file) => file.read_line(&mut input)?,
+			None => std::io::stdin().read_line(&mut input)?,
+		};
+		Ok(input.trim().to_string())
+	}
+
+	pub fn set_input_file(path: &std::path::Path) -> std::io::Result<()> {
+		*INPUT_FILE.lock().unwrap() = Some(BufReader::new(std::fs::File::open(path)?));
+		Ok(())
 	}
 
 	pub fn next_char(s: &str) -> (char, &str) {
@@ -31,15 +74,50 @@ impl Utils {
 		std::io::stdout().flush()
 	}
 
-	pub fn parse_sebek(input: &str) -> [Option<f64>; 3] {
+	pub fn parse_sebek(input: &str) -> Result<[Option<f64>; 3], String> {
 		let mut sebek = [None, None, None];
-		let args_vec = input.split('|').filter_map(|val| val.parse::<f64>().ok()).collect::<Vec<f64>>();
-		for (i, &val) in args_vec.iter().enumerate() {
-			if i >= sebek.len() {
-				break;
+		let parts = input.split('|').collect::<Vec<&str>>();
+		if parts.len() > sebek.len() {
+			return Err(format!("`{}` has {} numbers, but there are only 3 results of division by zero", input, parts.len()));
+		}
+		for (i, part) in parts.iter().enumerate() {
+			sebek[i] = Some(part.parse::<f64>().map_err(|_| format!("`{}` in `{}` is not a number", part, input))?);
+		}
+		Ok(sebek)
+	}
+
+	pub fn parse_non_finite(input: &str) -> Result<NonFinitePolicy, String> {
+		match input.to_lowercase().as_str() {
+			"error" => Ok(NonFinitePolicy::Error),
+			"saturate" => Ok(NonFinitePolicy::Saturate),
+			value => match value.parse::<f64>() {
+				Ok(number) if number.is_finite() => Ok(NonFinitePolicy::Replace(number)),
+				_ => Err(format!("`{}` is not a policy for NaN and infinite results, expected `error`, `saturate` or a finite number", input)),
+			},
+		}
+	}
+
+	// The candidate closest to the word, if it is close enough to be a likely typo
+	pub fn closest_match<'a>(word: &str, candidates: &[&'a str]) -> Option<&'a str> {
+		candidates
+			.iter()
+			.filter(|candidate| **candidate != word)
+			.map(|candidate| (Self::edit_distance(&word.to_lowercase(), &candidate.to_lowercase()), *candidate))
+			.filter(|(distance, candidate)| *distance <= (candidate.len() / 3).max(1))
+			.min_by_key(|(distance, _)| *distance)
+			.map(|(_, candidate)| candidate)
+	}
+
+	pub fn edit_distance(a: &str, b: &str) -> usize {
+		let b = b.chars().collect::<Vec<char>>();
+		let mut previous = (0..=b.len()).collect::<Vec<usize>>();
+		for (i, a_char) in a.chars().enumerate() {
+			let mut current = vec![i + 1];
+			for (j, b_char) in b.iter().enumerate() {
+				current.push((previous[j] + usize::from(a_char != *b_char)).min(previous[j + 1] + 1).min(current[j] + 1));
 			}
-			sebek[i] = Some(val);
+			previous = current;
 		}
-		sebek
+		previous[b.len()]
 	}
 }
@@ -0,0 +1,222 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use crate::{Diagnostic, Utils};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Warning {
+	TooLeftPointer,
+	NanProduced,
+	NonIntegerChar,
+	BreakOutsideLoop,
+	FractionalRepeat,
+}
+
+// The name used in the flags, the # statements and as the code of the message, and what the warning is about
+pub const WARNINGS: [(Warning, &str, &str); 5] = [
+	(Warning::TooLeftPointer, "too-left-pointer", "The pointer moved to the -1 index in memory"),
+	(Warning::NanProduced, "nan-produced", "An arithmetic command made the cell NaN (since 0.6.0)"),
+	(Warning::NonIntegerChar, "non-integer-char", "A cell with a fractional part was printed as a character (since 0.6.0)"),
+	(Warning::BreakOutsideLoop, "break-outside-loop", "A break command was executed outside of any loop (since 0.6.0)"),
+	(
+		Warning::FractionalRepeat,
+		"fractional-repeat",
+		"A repeat count was taken from a cell with a fractional part (since 0.6.0)",
+	),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WarningLevel {
+	Allow,
+	Warn,
+	Deny,
+}
+
+impl Warning {
+	pub fn name(&self) -> &'static str {
+		WARNINGS.iter().find(|(warning, _, _)| warning == self).unwrap().1
+	}
+
+	// The first version able to find the warning
+	pub fn since(&self) -> &'static str {
+		match self {
+			Warning::TooLeftPointer => "0.1.0",
+			_ => "0.6.0",
+		}
+	}
+
+	// `all` stands for all the warnings, and the dashes can be left out or written as underscores
+	pub fn parse(name: &str) -> Result<Vec<Self>, Option<&'static str>> {
+		let normalize = |name: &str| name.to_lowercase().replace(['-', '_'], "");
+		if normalize(name) == "all" {
+			return Ok(WARNINGS.iter().map(|(warning, _, _)| *warning).collect());
+		}
+		match WARNINGS.iter().find(|(_, candidate, _)| normalize(candidate) == normalize(name)) {
+			Some((warning, _, _)) => Ok(vec![*warning]),
+			None => {
+				let names = WARNINGS.iter().map(|(_, name, _)| *name).chain(["all"]).collect::<Vec<&str>>();
+				Err(Utils::closest_match(&name.to_lowercase(), &names))
+			}
+		}
+	}
+}
+
+// The levels chosen for the warnings, the ones not chosen are only printed
+#[derive(Clone, Debug, Default)]
+pub struct Warnings {
+	levels: BTreeMap<Warning, WarningLevel>,
+}
+
+impl Warnings {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn set(&mut self, warning: Warning, level: WarningLevel) {
+		self.levels.insert(warning, level);
+	}
+
+	pub fn level(&self, warning: Warning) -> WarningLevel {
+		self.levels.get(&warning).copied().unwrap_or(WarningLevel::Warn)
+	}
+
+	// The levels of the other ones win over these
+	pub fn merged(&self, other: &Self) -> Self {
+		let mut levels = self.levels.clone();
+		levels.extend(other.levels.iter());
+		Self { levels }
+	}
+
+	// The flags setting the same levels, for running the code in another process
+	pub fn arguments(&self) -> Vec<String> {
+		let mut arguments = vec![];
+		for (warning, level) in &self.levels {
+			let flag = match level {
+				WarningLevel::Allow => "--allow",
+				WarningLevel::Warn => "--warn",
+				WarningLevel::Deny => "--deny",
+			};
+			arguments.extend([String::from(flag), warning.name().to_string()]);
+		}
+		arguments
+	}
+
+	// The warnings given their own level that the version can't find, setting all of them at once isn't counted
+	pub fn unsupported(&self, can_find: impl Fn(Warning) -> bool) -> Vec<Warning> {
+		let first = self.levels.values().next();
+		if self.levels.len() == WARNINGS.len() && self.levels.values().all(|level| Some(level) == first) {
+			return vec![];
+		}
+		self.levels.keys().filter(|warning| !can_find(**warning)).copied().collect()
+	}
+
+	pub fn denied(warning: Warning, message: &str) -> String {
+		format!("{} (the `{}` warning is denied)", message, warning.name())
+	}
+}
+
+// The warnings found while running the code, each one is printed once for every command causing it
+#[derive(Clone, Debug, Default)]
+pub struct FoundWarnings {
+	pending: Vec<(Warning, String)>,
+	reported: HashSet<(Warning, usize)>,
+	denied: Option<Warning>,
+}
+
+impl FoundWarnings {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	// Keeps the warning to be printed after the command, or returns the error stopping the code if the warning is denied
+	pub fn found(&mut self, levels: &Warnings, warning: Warning, message: String) -> Result<(), String> {
+		match levels.level(warning) {
+			WarningLevel::Allow => Ok(()),
+			WarningLevel::Warn => {
+				self.pending.push((warning, message));
+				Ok(())
+			}
+			WarningLevel::Deny => {
+				self.denied = Some(warning);
+				Err(Warnings::denied(warning, &message))
+			}
+		}
+	}
+
+	// The denied warning that stopped the code, its name is the code of the error
+	pub fn denied(&self) -> Option<Warning> {
+		self.denied
+	}
+
+	// Prints the warnings found by the command at the position, the ones it already caused before are skipped
+	pub fn report(&mut self, position: usize, file_path: &Path, line: usize, column: usize) {
+		for (warning, message) in std::mem::take(&mut self.pending) {
+			if self.reported.insert((warning, position)) {
+				Diagnostic::warning(warning.name(), format!("{} at {}:{} in {:?}", message, line, column, file_path.file_name().unwrap_or_default()))
+					.at(file_path, line, column)
+					.emit();
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{FoundWarnings, Warning, WarningLevel, Warnings, WARNINGS};
+
+	fn levels(levels: &[(Warning, WarningLevel)]) -> Warnings {
+		let mut warnings = Warnings::new();
+		for (warning, level) in levels {
+			warnings.set(*warning, *level);
+		}
+		warnings
+	}
+
+	#[test]
+	fn levels_decide_what_happens_to_found_warnings() {
+		let warnings = levels(&[(Warning::TooLeftPointer, WarningLevel::Allow), (Warning::NanProduced, WarningLevel::Deny)]);
+		let mut found = FoundWarnings::new();
+		assert!(found.found(&warnings, Warning::TooLeftPointer, String::from("allowed")).is_ok());
+		assert!(found.found(&warnings, Warning::NonIntegerChar, String::from("printed")).is_ok());
+		assert_eq!(found.pending, vec![(Warning::NonIntegerChar, String::from("printed"))]);
+		assert_eq!(found.denied(), None);
+		let error = found.found(&warnings, Warning::NanProduced, String::from("denied")).unwrap_err();
+		assert_eq!(error, "denied (the `nan-produced` warning is denied)");
+		assert_eq!(found.denied(), Some(Warning::NanProduced));
+	}
+
+	#[test]
+	fn warnings_are_printed_once_for_each_command() {
+		let warnings = Warnings::new();
+		let mut found = FoundWarnings::new();
+		let path = std::path::Path::new("maumivu.au");
+		for position in [0, 0, 1] {
+			found.found(&warnings, Warning::TooLeftPointer, String::from("moved")).unwrap();
+			found.report(position, path, 1, position + 1);
+			assert!(found.pending.is_empty());
+		}
+		assert_eq!(found.reported.len(), 2);
+	}
+
+	#[test]
+	fn later_levels_win_when_merged() {
+		let project = levels(&[(Warning::TooLeftPointer, WarningLevel::Deny), (Warning::NanProduced, WarningLevel::Deny)]);
+		let flags = levels(&[(Warning::TooLeftPointer, WarningLevel::Allow)]);
+		let merged = project.merged(&flags);
+		assert_eq!(merged.level(Warning::TooLeftPointer), WarningLevel::Allow);
+		assert_eq!(merged.level(Warning::NanProduced), WarningLevel::Deny);
+		assert_eq!(merged.level(Warning::BreakOutsideLoop), WarningLevel::Warn);
+		assert_eq!(merged.arguments(), vec!["--allow", "too-left-pointer", "--deny", "nan-produced"]);
+	}
+
+	#[test]
+	fn levels_the_version_cant_use_are_found() {
+		let before_0_6_0 = |warning: Warning| warning.since() == "0.1.0";
+		let warnings = levels(&[(Warning::TooLeftPointer, WarningLevel::Deny), (Warning::FractionalRepeat, WarningLevel::Allow)]);
+		assert_eq!(warnings.unsupported(before_0_6_0), vec![Warning::FractionalRepeat]);
+		assert!(warnings.unsupported(|_| true).is_empty());
+		// `--deny all` is meant for any version
+		let all = levels(&WARNINGS.map(|(warning, _, _)| (warning, WarningLevel::Deny)));
+		assert!(all.unsupported(before_0_6_0).is_empty());
+	}
+}